@@ -0,0 +1,303 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::buyback::BuybackRef;
+
+#[ink::contract]
+mod buyback {
+    use ink::prelude::{string::String, vec::Vec};
+    use ink::env::{
+        call::{build_call, ExecutionInput, FromAddr, Selector},
+        DefaultEnvironment,
+    };
+    use ink::U256;
+    use pair::PairRef;
+    use psp_coin::{PSP22Error, PspCoinRef};
+
+    /// Selector of the pair's `swap(zero_for_one, amount_in, min_amount_out, to)`
+    const SWAP_SELECTOR: [u8; 4] = [0x11, 0x00, 0x4f, 0xa6];
+
+    /// Storage structure for the buyback-and-burn treasury
+    ///
+    /// Meant to be configured as the token's fee treasury. It accumulates
+    /// transfer fees in the token and revenue in the pair's other asset;
+    /// `buyback` swaps all revenue for the token and burns everything held.
+    #[ink(storage)]
+    pub struct Buyback {
+        /// Account allowed to trigger buybacks and change the pair
+        owner: Address,
+        /// Token bought back and burned
+        token: Address,
+        /// Pair of `token` and the revenue asset used for buybacks
+        pair: Address,
+        /// Tokens bought through the pair so far
+        total_bought: u128,
+        /// Tokens burned so far
+        total_burned: u128,
+    }
+
+    /// Event emitted when revenue is swapped for tokens
+    #[ink(event)]
+    pub struct BoughtBack {
+        #[ink(topic)]
+        pub pair: Address,
+        pub spent: u128,
+        pub bought: u128,
+    }
+
+    /// Event emitted when the treasury burns its tokens
+    #[ink(event)]
+    pub struct Burned {
+        pub amount: u128,
+    }
+
+    impl Buyback {
+        /// Constructor that buys back `token` through `pair`
+        #[ink(constructor)]
+        pub fn new(token: Address, pair: Address) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                token,
+                pair,
+                total_bought: 0,
+                total_burned: 0,
+            }
+        }
+    }
+
+    impl Buyback {
+        /// Returns the treasury owner
+        #[ink(message)]
+        pub fn owner(&self) -> Address {
+            self.owner
+        }
+
+        /// Returns the token bought back and burned
+        #[ink(message)]
+        pub fn token(&self) -> Address {
+            self.token
+        }
+
+        /// Returns the pair used for buybacks
+        #[ink(message)]
+        pub fn pair(&self) -> Address {
+            self.pair
+        }
+
+        /// Returns the tokens bought and burned so far
+        #[ink(message)]
+        pub fn totals(&self) -> (u128, u128) {
+            (self.total_bought, self.total_burned)
+        }
+
+        /// Accept native revenue
+        #[ink(message, payable)]
+        pub fn receive(&mut self) {}
+
+        /// Change the pair used for buybacks (owner only)
+        #[ink(message)]
+        pub fn set_pair(&mut self, pair: Address) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.pair = pair;
+            Ok(())
+        }
+
+        /// Swap all revenue for at least `min_out` tokens, then burn every
+        /// token the treasury holds, including collected fees (owner only)
+        #[ink(message)]
+        pub fn buyback(&mut self, min_out: u128) -> Result<(u128, u128), PSP22Error> {
+            self.ensure_owner()?;
+
+            let this = self.env().address();
+            let pair: PairRef = FromAddr::from_addr(self.pair);
+            let (token0, token1) = pair.tokens();
+            let (revenue_asset, zero_for_one) = if token0 == self.token {
+                (token1, false)
+            } else if token1 == Some(self.token) {
+                (Some(token0), true)
+            } else {
+                return Err(PSP22Error::Custom(String::from("Pair does not trade the token")));
+            };
+
+            // Spend the whole revenue balance
+            let (spent, value) = match revenue_asset {
+                Some(asset) => {
+                    let mut asset: PspCoinRef = FromAddr::from_addr(asset);
+                    let balance = asset.balance_of(this);
+                    asset.approve(self.pair, balance)?;
+                    (balance, U256::zero())
+                }
+                None => {
+                    let balance = self.env().balance();
+                    if balance > U256::from(u128::MAX) {
+                        return Err(PSP22Error::Custom(String::from("Overflow")));
+                    }
+                    (balance.low_u128(), balance)
+                }
+            };
+
+            let bought = if spent > 0 {
+                self.swap(value, zero_for_one, spent, min_out)?
+            } else if min_out > 0 {
+                return Err(PSP22Error::Custom(String::from("Slippage exceeded")));
+            } else {
+                0
+            };
+            self.total_bought = self.total_bought.saturating_add(bought);
+            if bought > 0 {
+                self.env().emit_event(BoughtBack {
+                    pair: self.pair,
+                    spent,
+                    bought,
+                });
+            }
+
+            let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+            let burned = token.balance_of(this);
+            token.burn(burned)?;
+            self.total_burned = self.total_burned.saturating_add(burned);
+            self.env().emit_event(Burned { amount: burned });
+
+            Ok((bought, burned))
+        }
+    }
+
+    impl Buyback {
+        /// Fails unless the caller is the treasury owner
+        fn ensure_owner(&self) -> Result<(), PSP22Error> {
+            if self.env().caller() != self.owner {
+                return Err(PSP22Error::Unauthorized);
+            }
+            Ok(())
+        }
+
+        /// Swap `amount_in` of revenue for tokens sent to the treasury
+        fn swap(&self, value: U256, zero_for_one: bool, amount_in: u128, min_out: u128) -> Result<u128, PSP22Error> {
+            let result = build_call::<DefaultEnvironment>()
+                .call(self.pair)
+                .transferred_value(value)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(SWAP_SELECTOR))
+                        .push_arg(zero_for_one)
+                        .push_arg(amount_in)
+                        .push_arg(min_out)
+                        .push_arg(self.env().address()),
+                )
+                .returns::<Result<u128, PSP22Error>>()
+                .try_invoke();
+
+            match result {
+                Ok(Ok(Ok(bought))) => Ok(bought),
+                Ok(Ok(Err(error))) => Err(error),
+                _ => Err(PSP22Error::Custom(String::from("Swap call failed"))),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn buyback_is_owner_only() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut treasury = Buyback::new(accounts.django, accounts.eve);
+            assert_eq!(treasury.owner(), accounts.alice);
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(treasury.buyback(0), Err(PSP22Error::Unauthorized));
+            assert_eq!(treasury.set_pair(accounts.frank), Err(PSP22Error::Unauthorized));
+            assert_eq!(treasury.totals(), (0, 0));
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use pair::Pair;
+        use psp_coin::PspCoin;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn buyback_swaps_revenue_and_burns<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a token/native pair and a treasury holding fees and revenue
+            let mut token_constructor = PspCoinRef::new_with_supply(1_000_000);
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut pair_constructor = PairRef::new(token.addr, None);
+            let pair = client
+                .instantiate("pair", &ink_e2e::alice(), &mut pair_constructor)
+                .submit()
+                .await
+                .expect("pair instantiate failed");
+            let mut pair_call = pair.call_builder::<Pair>();
+
+            client
+                .call(&ink_e2e::alice(), &token_call.approve(pair.addr, 10_000))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::alice(), &pair_call.add_liquidity(10_000, 10_000, 0))
+                .value(10_000)
+                .submit()
+                .await?;
+
+            let mut treasury_constructor = BuybackRef::new(token.addr, pair.addr);
+            let treasury = client
+                .instantiate("buyback", &ink_e2e::alice(), &mut treasury_constructor)
+                .submit()
+                .await
+                .expect("treasury instantiate failed");
+            let mut treasury_call = treasury.call_builder::<Buyback>();
+
+            client
+                .call(&ink_e2e::alice(), &token_call.transfer(treasury.addr, 100, Vec::new()))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::alice(), &treasury_call.receive())
+                .value(1_000)
+                .submit()
+                .await?;
+
+            // When the owner triggers a buyback
+            let supply_before = client
+                .call(&ink_e2e::alice(), &token_call.total_supply())
+                .dry_run()
+                .await?
+                .return_value();
+            client
+                .call(&ink_e2e::alice(), &treasury_call.buyback(900))
+                .submit()
+                .await?;
+
+            // Then the revenue was swapped and all held tokens were burned
+            let (bought, burned) = client
+                .call(&ink_e2e::alice(), &treasury_call.totals())
+                .dry_run()
+                .await?
+                .return_value();
+            assert!(bought >= 900);
+            assert_eq!(burned, bought + 100);
+
+            let supply_after = client
+                .call(&ink_e2e::alice(), &token_call.total_supply())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(supply_before - supply_after, burned);
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,508 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::compounding_vault::CompoundingVaultRef;
+
+#[ink::contract]
+mod compounding_vault {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use ink::env::call::FromAddr;
+    use psp_coin::{PSP22Error, PspCoinRef};
+
+    /// Denominator for the harvest incentive in basis points
+    const BPS_DENOMINATOR: u128 = 10_000;
+
+    /// Storage structure for the auto-compounding vault
+    ///
+    /// Deposits are staked in the token's staking module; `harvest` claims
+    /// the staking rewards and restakes them, so each share is backed by a
+    /// growing amount of staked tokens.
+    ///
+    /// The token merges all unbonding of the vault into one request, so
+    /// redemptions are batched into epochs: an epoch collects redemptions
+    /// while the previous one unbonds, and is only unstaked as a whole once
+    /// that one is released. Later redemptions never delay an epoch already
+    /// unbonding.
+    #[ink(storage)]
+    pub struct CompoundingVault {
+        /// Token that is deposited, staked and paid out as rewards
+        token: Address,
+        /// Share of each harvest paid to its caller, in basis points
+        harvest_incentive_bps: u16,
+        /// Total supply of vault shares
+        total_supply: u128,
+        /// Mapping from account to share balance
+        balances: Mapping<Address, u128>,
+        /// Redeemed tokens waiting to be paid out, per account and epoch
+        pending_withdrawals: Mapping<(Address, u32), u128>,
+        /// Epoch currently collecting redemptions
+        epoch: u32,
+        /// Tokens redeemed in the current epoch, still staked
+        queued_assets: u128,
+        /// Epoch currently unbonding in the token, with its release time
+        unbonding: Option<(u32, Timestamp)>,
+    }
+
+    /// Event emitted when tokens are deposited for shares
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        pub account: Address,
+        pub assets: u128,
+        pub shares: u128,
+    }
+
+    /// Event emitted when shares are redeemed and start unbonding
+    #[ink(event)]
+    pub struct Redeemed {
+        #[ink(topic)]
+        pub account: Address,
+        pub assets: u128,
+        pub shares: u128,
+        pub epoch: u32,
+    }
+
+    /// Event emitted when staking rewards are compounded
+    #[ink(event)]
+    pub struct Harvested {
+        #[ink(topic)]
+        pub caller: Address,
+        pub compounded: u128,
+        pub incentive: u128,
+    }
+
+    impl CompoundingVault {
+        /// Constructor that creates a vault compounding `token` staking rewards
+        #[ink(constructor)]
+        pub fn new(token: Address, harvest_incentive_bps: u16) -> Self {
+            Self {
+                token,
+                harvest_incentive_bps,
+                total_supply: 0,
+                balances: Mapping::default(),
+                pending_withdrawals: Mapping::default(),
+                epoch: 0,
+                queued_assets: 0,
+                unbonding: None,
+            }
+        }
+    }
+
+    impl CompoundingVault {
+        /// Returns the total share supply
+        #[ink(message)]
+        pub fn total_supply(&self) -> u128 {
+            self.total_supply
+        }
+
+        /// Returns the share balance of the specified owner
+        #[ink(message)]
+        pub fn balance_of(&self, owner: Address) -> u128 {
+            self.balances.get(owner).unwrap_or(0)
+        }
+
+        /// Returns the tokens currently staked on behalf of shareholders
+        ///
+        /// Tokens redeemed in the current epoch are still staked but no
+        /// longer back any shares, so they are left out.
+        #[ink(message)]
+        pub fn total_assets(&self) -> u128 {
+            let token: PspCoinRef = FromAddr::from_addr(self.token);
+            token
+                .staked_balance_of(self.env().address())
+                .saturating_sub(self.queued_assets)
+        }
+
+        /// Returns the tokens `shares` are currently worth
+        #[ink(message)]
+        pub fn convert_to_assets(&self, shares: u128) -> u128 {
+            Self::mul_div(shares, self.total_assets().saturating_add(1), self.total_supply.saturating_add(1))
+        }
+
+        /// Returns the tokens `account` redeemed in `epoch` and has not claimed
+        #[ink(message)]
+        pub fn pending_withdrawal_of(&self, account: Address, epoch: u32) -> u128 {
+            self.pending_withdrawals.get((account, epoch)).unwrap_or(0)
+        }
+
+        /// Returns the epoch currently collecting redemptions
+        #[ink(message)]
+        pub fn epoch(&self) -> u32 {
+            self.epoch
+        }
+
+        /// Returns the epoch currently unbonding and when it is released
+        #[ink(message)]
+        pub fn unbonding_epoch(&self) -> Option<(u32, Timestamp)> {
+            self.unbonding
+        }
+
+        /// Transfer shares from caller to recipient
+        #[ink(message)]
+        pub fn transfer(&mut self, to: Address, value: u128) -> Result<(), PSP22Error> {
+            let from = self.env().caller();
+            if from == to || value == 0 {
+                return Ok(());
+            }
+
+            let new_from_balance = self
+                .balance_of(from)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.balances.insert(from, &new_from_balance);
+            self.balances.insert(to, &(self.balance_of(to) + value));
+
+            Ok(())
+        }
+
+        /// Deposit `assets` tokens from the caller and stake them for shares
+        ///
+        /// The caller must have approved the vault to spend `assets`. Shares
+        /// are issued for the amount actually received, after any transfer
+        /// fee.
+        #[ink(message)]
+        pub fn deposit(&mut self, assets: u128) -> Result<u128, PSP22Error> {
+            let account = self.env().caller();
+            let this = self.env().address();
+
+            let total_assets = self.total_assets();
+            let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+            let balance_before = token.balance_of(this);
+            token.transfer_from(account, this, assets, Vec::new())?;
+            let assets = token.balance_of(this).saturating_sub(balance_before);
+
+            let shares = Self::mul_div(assets, self.total_supply.saturating_add(1), total_assets.saturating_add(1));
+            if shares == 0 {
+                return Err(PSP22Error::Custom(String::from("Deposit too small")));
+            }
+            token.stake(assets)?;
+
+            self.total_supply = self
+                .total_supply
+                .checked_add(shares)
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+            self.balances.insert(account, &(self.balance_of(account) + shares));
+
+            self.env().emit_event(Deposited {
+                account,
+                assets,
+                shares,
+            });
+
+            Ok(shares)
+        }
+
+        /// Burn `shares` and queue the tokens they are worth in the current
+        /// epoch, returning that amount
+        ///
+        /// The epoch starts unbonding right away if no other epoch is.
+        #[ink(message)]
+        pub fn redeem(&mut self, shares: u128) -> Result<u128, PSP22Error> {
+            let account = self.env().caller();
+
+            let assets = self.convert_to_assets(shares);
+            if assets == 0 {
+                return Err(PSP22Error::Custom(String::from("Redemption too small")));
+            }
+            let new_balance = self
+                .balance_of(account)
+                .checked_sub(shares)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.balances.insert(account, &new_balance);
+            self.total_supply -= shares;
+
+            let epoch = self.epoch;
+            self.queued_assets += assets;
+            self.pending_withdrawals
+                .insert((account, epoch), &(self.pending_withdrawal_of(account, epoch) + assets));
+            self.advance_epochs()?;
+
+            self.env().emit_event(Redeemed {
+                account,
+                assets,
+                shares,
+                epoch,
+            });
+
+            Ok(assets)
+        }
+
+        /// Pay out the tokens the caller redeemed in `epoch` once that epoch
+        /// finished unbonding
+        #[ink(message)]
+        pub fn claim_withdrawal(&mut self, epoch: u32) -> Result<u128, PSP22Error> {
+            let account = self.env().caller();
+
+            let amount = self.pending_withdrawal_of(account, epoch);
+            if amount == 0 {
+                return Err(PSP22Error::Custom(String::from("Nothing to withdraw")));
+            }
+
+            self.advance_epochs()?;
+            if !self.is_released(epoch) {
+                return Err(PSP22Error::Custom(String::from("Still unbonding")));
+            }
+
+            self.pending_withdrawals.remove((account, epoch));
+            let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+            token.transfer(account, amount, Vec::new())?;
+
+            Ok(amount)
+        }
+
+        /// Claim the vault's staking rewards and restake them, paying the
+        /// caller an incentive
+        #[ink(message)]
+        pub fn harvest(&mut self) -> Result<u128, PSP22Error> {
+            let caller = self.env().caller();
+
+            let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+            let rewards = token.claim_rewards()?;
+
+            let incentive = rewards.saturating_mul(u128::from(self.harvest_incentive_bps)) / BPS_DENOMINATOR;
+            let compounded = rewards - incentive;
+
+            token.transfer(caller, incentive, Vec::new())?;
+            token.stake(compounded)?;
+
+            self.env().emit_event(Harvested {
+                caller,
+                compounded,
+                incentive,
+            });
+
+            Ok(compounded)
+        }
+    }
+
+    impl CompoundingVault {
+        /// Withdraw the unbonding epoch once it is released, then start
+        /// unbonding the current one if it has any redemptions
+        fn advance_epochs(&mut self) -> Result<(), PSP22Error> {
+            let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+
+            if let Some((_, release_at)) = self.unbonding {
+                if self.env().block_timestamp() < release_at {
+                    return Ok(());
+                }
+                token.withdraw()?;
+                self.unbonding = None;
+            }
+
+            if self.queued_assets > 0 {
+                token.request_unstake(self.queued_assets)?;
+                let (_, release_at) = token.unbonding_of(self.env().address());
+                self.unbonding = Some((self.epoch, release_at));
+                self.queued_assets = 0;
+                self.epoch = self
+                    .epoch
+                    .checked_add(1)
+                    .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+            }
+
+            Ok(())
+        }
+
+        /// Whether the tokens redeemed in `epoch` have been withdrawn from
+        /// the token and can be paid out
+        ///
+        /// Epochs unbond one after another, so every epoch that has been
+        /// unstaked and is not the one unbonding is released.
+        fn is_released(&self, epoch: u32) -> bool {
+            epoch < self.epoch && self.unbonding.map_or(true, |(unbonding, _)| unbonding != epoch)
+        }
+
+        /// Computes `a * b / denominator` rounding down, saturating on overflow
+        fn mul_div(a: u128, b: u128, denominator: u128) -> u128 {
+            let quotient = ink::U256::from(a) * ink::U256::from(b) / ink::U256::from(denominator);
+            if quotient > ink::U256::from(u128::MAX) {
+                return u128::MAX;
+            }
+            quotient.low_u128()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn mul_div_works() {
+            assert_eq!(CompoundingVault::mul_div(100, 1, 1), 100);
+            assert_eq!(CompoundingVault::mul_div(100, 101, 201), 50);
+            assert_eq!(CompoundingVault::mul_div(u128::MAX, 3, 1), u128::MAX);
+        }
+
+        #[ink::test]
+        fn only_unstaked_epochs_no_longer_unbonding_are_released() {
+            let accounts = ink::env::test::default_accounts();
+            let mut vault = CompoundingVault::new(accounts.django, 100);
+            assert!(!vault.is_released(0));
+
+            // Epoch 0 unbonding while epoch 1 collects redemptions
+            vault.epoch = 1;
+            vault.unbonding = Some((0, 1_000));
+            assert!(!vault.is_released(0));
+            assert!(!vault.is_released(1));
+
+            // Epoch 1 unbonding after epoch 0 was withdrawn
+            vault.epoch = 2;
+            vault.unbonding = Some((1, 2_000));
+            assert!(vault.is_released(0));
+            assert!(!vault.is_released(1));
+
+            vault.unbonding = None;
+            assert!(vault.is_released(1));
+            assert!(!vault.is_released(2));
+        }
+
+        #[ink::test]
+        fn share_transfer_works() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut vault = CompoundingVault::new(accounts.django, 100);
+            vault.balances.insert(accounts.alice, &100);
+
+            assert_eq!(vault.transfer(accounts.bob, 30), Ok(()));
+            assert_eq!(vault.balance_of(accounts.bob), 30);
+            assert_eq!(
+                vault.transfer(accounts.bob, 100),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::PspCoin;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn harvest_grows_value_of_all_shares<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a token paying staking rewards and a vault on top of it
+            let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+            let charlie = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::charlie());
+
+            let mut token_constructor = PspCoinRef::new_with_supply(1_000_000);
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut vault_constructor = CompoundingVaultRef::new(token.addr, 100);
+            let vault = client
+                .instantiate("compounding_vault", &ink_e2e::alice(), &mut vault_constructor)
+                .submit()
+                .await
+                .expect("vault instantiate failed");
+            let mut vault_call = vault.call_builder::<CompoundingVault>();
+
+            client
+                .call(&ink_e2e::alice(), &token_call.fund_rewards(100_000))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::alice(), &token_call.set_reward_rate(1_000))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::alice(), &token_call.set_unbonding_period(86_400_000))
+                .submit()
+                .await?;
+            for user in [bob, charlie] {
+                client
+                    .call(&ink_e2e::alice(), &token_call.transfer(user, 10_000, Vec::new()))
+                    .submit()
+                    .await?;
+            }
+
+            // When bob and charlie deposit different amounts
+            for (signer, amount) in [(ink_e2e::bob(), 1_000u128), (ink_e2e::charlie(), 3_000u128)] {
+                client
+                    .call(&signer, &token_call.approve(vault.addr, amount))
+                    .submit()
+                    .await?;
+                client
+                    .call(&signer, &vault_call.deposit(amount))
+                    .submit()
+                    .await?;
+            }
+
+            client
+                .call(&ink_e2e::alice(), &vault_call.harvest())
+                .submit()
+                .await?;
+
+            // Then both shareholders gained in proportion to their shares
+            let bob_shares = client
+                .call(&ink_e2e::bob(), &vault_call.balance_of(bob))
+                .dry_run()
+                .await?
+                .return_value();
+            let charlie_shares = client
+                .call(&ink_e2e::charlie(), &vault_call.balance_of(charlie))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(charlie_shares, bob_shares * 3);
+
+            let bob_assets = client
+                .call(&ink_e2e::bob(), &vault_call.convert_to_assets(bob_shares))
+                .dry_run()
+                .await?
+                .return_value();
+            let charlie_assets = client
+                .call(&ink_e2e::charlie(), &vault_call.convert_to_assets(charlie_shares))
+                .dry_run()
+                .await?
+                .return_value();
+            assert!(bob_assets > 1_000);
+            assert!(charlie_assets > 3_000);
+
+            // And redeeming starts unbonding the grown amount
+            client
+                .call(&ink_e2e::bob(), &vault_call.redeem(bob_shares))
+                .submit()
+                .await?;
+            let pending = client
+                .call(&ink_e2e::bob(), &vault_call.pending_withdrawal_of(bob, 0))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(pending, bob_assets);
+            let unbonding = client
+                .call(&ink_e2e::bob(), &vault_call.unbonding_epoch())
+                .dry_run()
+                .await?
+                .return_value();
+
+            // While a later redemption waits in the next epoch without
+            // delaying bob's
+            client
+                .call(&ink_e2e::charlie(), &vault_call.redeem(1))
+                .submit()
+                .await?;
+            let charlie_pending = client
+                .call(&ink_e2e::charlie(), &vault_call.pending_withdrawal_of(charlie, 1))
+                .dry_run()
+                .await?
+                .return_value();
+            assert!(charlie_pending > 0);
+            let still_unbonding = client
+                .call(&ink_e2e::charlie(), &vault_call.unbonding_epoch())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(still_unbonding, unbonding);
+
+            Ok(())
+        }
+    }
+}
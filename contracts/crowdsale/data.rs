@@ -0,0 +1,75 @@
+use ink::U256;
+use psp_coin::PSP22Error;
+
+/// Crowdsale error types
+#[derive(Debug, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum SaleError {
+    /// Caller is not the sale owner
+    Unauthorized,
+    /// Sale has not started yet or has already ended
+    NotOpen,
+    /// Sale has not ended yet
+    NotClosed,
+    /// Opening time is not before closing time or the rate is zero
+    InvalidConfig,
+    /// No native value was sent
+    ZeroPurchase,
+    /// Purchase would raise more than the hard cap
+    HardCapExceeded,
+    /// Purchase would exceed the buyer's contribution limit
+    ContributionLimitExceeded,
+    /// Buyer is not assigned to a tier while tiers are enforced
+    NotAllowlisted,
+    /// No tokens have vested since the last release
+    NothingToRelease,
+    /// Sending native currency failed
+    TransferFailed,
+    /// The token rejected the delivery
+    Token(PSP22Error),
+    /// Arithmetic overflow
+    Overflow,
+}
+
+impl From<PSP22Error> for SaleError {
+    fn from(error: PSP22Error) -> Self {
+        SaleError::Token(error)
+    }
+}
+
+/// Price and allocation of an allowlisted group of buyers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Tier {
+    /// Tokens delivered per unit of native currency paid
+    pub rate: u128,
+    /// Maximum native currency a single buyer of the tier may contribute
+    pub max_contribution: U256,
+}
+
+/// Purchased tokens released linearly after the sale closes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct VestingSchedule {
+    /// Tokens bought by the beneficiary
+    pub total: u128,
+    /// Tokens already released to the beneficiary
+    pub released: u128,
+}
+
+/// Price schedule of a sale in Dutch auction mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct DutchAuction {
+    /// Price at the opening time, in native currency per `unit` tokens
+    pub start_price: U256,
+    /// Price reached at the closing time, in native currency per `unit` tokens
+    pub floor_price: U256,
+    /// Token base units the prices refer to, usually `10^decimals`
+    pub unit: u128,
+    /// Tokens offered; the sale closes once they are sold
+    pub supply: u128,
+}
@@ -0,0 +1,779 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+mod data;
+
+pub use data::{DutchAuction, SaleError, Tier, VestingSchedule};
+pub use self::crowdsale::CrowdsaleRef;
+
+#[ink::contract]
+mod crowdsale {
+    use ink::{storage::Mapping, prelude::vec::Vec};
+    use ink::env::call::FromAddr;
+    use ink::U256;
+    use psp_coin::PspCoinRef;
+
+    use crate::data::{DutchAuction, SaleError, Tier, VestingSchedule};
+
+    /// Storage structure for the crowdsale
+    ///
+    /// Buyers pay native currency and receive `rate` tokens per unit paid,
+    /// either minted on purchase (the sale must own the token) or
+    /// transferred from an allocation held by the sale. With tiers enforced,
+    /// only buyers assigned to a tier may buy, at the tier's price and limit.
+    /// With vesting configured, purchased tokens are held by the sale and
+    /// released linearly from the closing time. In Dutch auction mode the
+    /// price falls linearly over the sale window instead of using `rate`.
+    #[ink(storage)]
+    pub struct Crowdsale {
+        /// Account allowed to withdraw proceeds and unsold tokens
+        owner: Address,
+        /// Token being sold
+        token: Address,
+        /// Tokens delivered per unit of native currency paid
+        rate: u128,
+        /// Maximum native currency the sale may raise
+        hard_cap: U256,
+        /// Maximum native currency a single account may contribute
+        max_contribution: U256,
+        /// Time at which purchases open
+        opening_time: Timestamp,
+        /// Time at which purchases close
+        closing_time: Timestamp,
+        /// Whether tokens are minted on purchase instead of transferred
+        mint_on_purchase: bool,
+        /// Native currency raised so far
+        raised: U256,
+        /// Tokens delivered so far
+        tokens_sold: u128,
+        /// Native currency contributed by each buyer
+        contributions: Mapping<Address, U256>,
+        /// Whether only buyers assigned to a tier may buy
+        tiers_enabled: bool,
+        /// Tiers by id
+        tiers: Mapping<u8, Tier>,
+        /// Tier each allowlisted buyer belongs to
+        buyer_tiers: Mapping<Address, u8>,
+        /// Cliff and duration of the vesting applied to purchases, if any
+        vesting: Option<(Timestamp, Timestamp)>,
+        /// Vesting schedule of each buyer
+        vesting_schedules: Mapping<Address, VestingSchedule>,
+        /// Purchased tokens held by the sale and not yet released
+        vesting_locked: u128,
+        /// Price schedule when the sale runs as a Dutch auction
+        dutch_auction: Option<DutchAuction>,
+    }
+
+    /// Event emitted when tokens are bought
+    #[ink(event)]
+    pub struct TokensPurchased {
+        #[ink(topic)]
+        pub buyer: Address,
+        pub paid: U256,
+        pub amount: u128,
+    }
+
+    /// Event emitted when vested tokens are released to a buyer
+    #[ink(event)]
+    pub struct VestedTokensReleased {
+        #[ink(topic)]
+        pub buyer: Address,
+        pub amount: u128,
+    }
+
+    /// Event emitted when the owner withdraws sale proceeds
+    #[ink(event)]
+    pub struct ProceedsWithdrawn {
+        #[ink(topic)]
+        pub to: Address,
+        pub amount: U256,
+    }
+
+    impl Crowdsale {
+        /// Constructor that configures the sale of `token`
+        #[ink(constructor)]
+        pub fn new(
+            token: Address,
+            rate: u128,
+            hard_cap: U256,
+            max_contribution: U256,
+            opening_time: Timestamp,
+            closing_time: Timestamp,
+            mint_on_purchase: bool,
+        ) -> Result<Self, SaleError> {
+            if rate == 0 || opening_time >= closing_time {
+                return Err(SaleError::InvalidConfig);
+            }
+
+            Ok(Self {
+                owner: Self::env().caller(),
+                token,
+                rate,
+                hard_cap,
+                max_contribution,
+                opening_time,
+                closing_time,
+                mint_on_purchase,
+                raised: U256::zero(),
+                tokens_sold: 0,
+                contributions: Mapping::default(),
+                tiers_enabled: false,
+                tiers: Mapping::default(),
+                buyer_tiers: Mapping::default(),
+                vesting: None,
+                vesting_schedules: Mapping::default(),
+                vesting_locked: 0,
+                dutch_auction: None,
+            })
+        }
+    }
+
+    impl Crowdsale {
+        /// Returns the sale owner
+        #[ink(message)]
+        pub fn owner(&self) -> Address {
+            self.owner
+        }
+
+        /// Returns the token being sold
+        #[ink(message)]
+        pub fn token(&self) -> Address {
+            self.token
+        }
+
+        /// Returns the tokens delivered per unit of native currency
+        #[ink(message)]
+        pub fn rate(&self) -> u128 {
+            self.rate
+        }
+
+        /// Returns the hard cap and the per-account contribution limit
+        #[ink(message)]
+        pub fn caps(&self) -> (U256, U256) {
+            (self.hard_cap, self.max_contribution)
+        }
+
+        /// Returns the opening and closing time
+        #[ink(message)]
+        pub fn sale_window(&self) -> (Timestamp, Timestamp) {
+            (self.opening_time, self.closing_time)
+        }
+
+        /// Returns the native currency raised so far
+        #[ink(message)]
+        pub fn raised(&self) -> U256 {
+            self.raised
+        }
+
+        /// Returns the tokens delivered so far
+        #[ink(message)]
+        pub fn tokens_sold(&self) -> u128 {
+            self.tokens_sold
+        }
+
+        /// Returns the native currency contributed by `buyer`
+        #[ink(message)]
+        pub fn contribution_of(&self, buyer: Address) -> U256 {
+            self.contributions.get(buyer).unwrap_or_default()
+        }
+
+        /// Returns whether purchases are currently accepted
+        #[ink(message)]
+        pub fn is_open(&self) -> bool {
+            let now = self.env().block_timestamp();
+            let sold_out = self
+                .dutch_auction
+                .is_some_and(|auction| self.tokens_sold >= auction.supply);
+
+            now >= self.opening_time
+                && now < self.closing_time
+                && self.raised < self.hard_cap
+                && !sold_out
+        }
+
+        /// Buy tokens with the transferred native value
+        ///
+        /// In Dutch auction mode a purchase larger than the remaining supply
+        /// buys the rest and refunds the excess.
+        #[ink(message, payable)]
+        pub fn buy(&mut self) -> Result<u128, SaleError> {
+            let buyer = self.env().caller();
+            let paid = self.env().transferred_value();
+
+            if !self.is_open() {
+                return Err(SaleError::NotOpen);
+            }
+            if paid.is_zero() {
+                return Err(SaleError::ZeroPurchase);
+            }
+            let (rate, max_contribution) = self.terms_for(buyer)?;
+            let (amount, cost) = match self.dutch_auction {
+                Some(auction) => self.dutch_purchase(&auction, paid)?,
+                None => (Self::fixed_rate_purchase(rate, paid)?, paid),
+            };
+
+            let raised = self.raised.checked_add(cost).ok_or(SaleError::Overflow)?;
+            if raised > self.hard_cap {
+                return Err(SaleError::HardCapExceeded);
+            }
+            let contribution = self
+                .contribution_of(buyer)
+                .checked_add(cost)
+                .ok_or(SaleError::Overflow)?;
+            if contribution > max_contribution {
+                return Err(SaleError::ContributionLimitExceeded);
+            }
+
+            self.raised = raised;
+            self.contributions.insert(buyer, &contribution);
+            self.tokens_sold = self
+                .tokens_sold
+                .checked_add(amount)
+                .ok_or(SaleError::Overflow)?;
+
+            if self.vesting.is_some() {
+                self.lock_for_vesting(buyer, amount)?;
+            } else {
+                self.deliver(buyer, amount)?;
+            }
+
+            if cost < paid {
+                self.env()
+                    .transfer(buyer, paid - cost)
+                    .map_err(|_| SaleError::TransferFailed)?;
+            }
+
+            self.env().emit_event(TokensPurchased {
+                buyer,
+                paid: cost,
+                amount,
+            });
+
+            Ok(amount)
+        }
+
+        /// Send all raised native currency to `to` (owner only)
+        #[ink(message)]
+        pub fn withdraw_proceeds(&mut self, to: Address) -> Result<U256, SaleError> {
+            self.ensure_owner()?;
+
+            let amount = self.env().balance();
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| SaleError::TransferFailed)?;
+
+            self.env().emit_event(ProceedsWithdrawn { to, amount });
+
+            Ok(amount)
+        }
+
+        /// Send the unsold token allocation to `to` once the sale closed (owner only)
+        #[ink(message)]
+        pub fn withdraw_unsold(&mut self, to: Address) -> Result<u128, SaleError> {
+            self.ensure_owner()?;
+
+            if self.env().block_timestamp() < self.closing_time {
+                return Err(SaleError::NotClosed);
+            }
+
+            let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+            let unsold = token
+                .balance_of(self.env().address())
+                .saturating_sub(self.vesting_locked);
+            token.transfer(to, unsold, Vec::new())?;
+
+            Ok(unsold)
+        }
+
+        /// Returns whether only buyers assigned to a tier may buy
+        #[ink(message)]
+        pub fn tiers_enabled(&self) -> bool {
+            self.tiers_enabled
+        }
+
+        /// Returns the tier with the given id
+        #[ink(message)]
+        pub fn tier(&self, tier_id: u8) -> Option<Tier> {
+            self.tiers.get(tier_id)
+        }
+
+        /// Returns the tier `buyer` is assigned to
+        #[ink(message)]
+        pub fn tier_of(&self, buyer: Address) -> Option<u8> {
+            self.buyer_tiers.get(buyer)
+        }
+
+        /// Enable or disable tier enforcement (owner only)
+        #[ink(message)]
+        pub fn set_tiers_enabled(&mut self, enabled: bool) -> Result<(), SaleError> {
+            self.ensure_owner()?;
+            self.tiers_enabled = enabled;
+            Ok(())
+        }
+
+        /// Create or update a tier (owner only)
+        #[ink(message)]
+        pub fn set_tier(&mut self, tier_id: u8, rate: u128, max_contribution: U256) -> Result<(), SaleError> {
+            self.ensure_owner()?;
+
+            if rate == 0 {
+                return Err(SaleError::InvalidConfig);
+            }
+            self.tiers.insert(
+                tier_id,
+                &Tier {
+                    rate,
+                    max_contribution,
+                },
+            );
+
+            Ok(())
+        }
+
+        /// Assign `buyers` to a tier, or remove them from the allowlist with
+        /// `None` (owner only)
+        #[ink(message)]
+        pub fn assign_tier(&mut self, buyers: Vec<Address>, tier_id: Option<u8>) -> Result<(), SaleError> {
+            self.ensure_owner()?;
+
+            if let Some(tier_id) = tier_id {
+                if !self.tiers.contains(tier_id) {
+                    return Err(SaleError::InvalidConfig);
+                }
+            }
+            for buyer in buyers {
+                if let Some(tier_id) = tier_id {
+                    self.buyer_tiers.insert(buyer, &tier_id);
+                } else {
+                    self.buyer_tiers.remove(buyer);
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Returns the cliff and duration of the purchase vesting, if any
+        #[ink(message)]
+        pub fn vesting(&self) -> Option<(Timestamp, Timestamp)> {
+            self.vesting
+        }
+
+        /// Returns the vesting schedule of `buyer`
+        #[ink(message)]
+        pub fn vesting_schedule(&self, buyer: Address) -> VestingSchedule {
+            self.vesting_schedules.get(buyer).unwrap_or_default()
+        }
+
+        /// Returns the purchased tokens of `buyer` vested so far, released or not
+        #[ink(message)]
+        pub fn vested_amount(&self, buyer: Address) -> u128 {
+            let Some((cliff, duration)) = self.vesting else {
+                return 0;
+            };
+            let total = self.vesting_schedule(buyer).total;
+
+            let now = self.env().block_timestamp();
+            if now < self.closing_time.saturating_add(cliff) {
+                return 0;
+            }
+            let elapsed = now - self.closing_time;
+            if elapsed >= duration {
+                return total;
+            }
+
+            (U256::from(total) * U256::from(elapsed) / U256::from(duration)).low_u128()
+        }
+
+        /// Vest purchases over `duration` after the sale closes, releasing
+        /// nothing before `cliff`, or deliver them immediately with `None`
+        ///
+        /// Only possible before the sale opens (owner only).
+        #[ink(message)]
+        pub fn set_vesting(&mut self, vesting: Option<(Timestamp, Timestamp)>) -> Result<(), SaleError> {
+            self.ensure_owner()?;
+
+            if self.env().block_timestamp() >= self.opening_time {
+                return Err(SaleError::InvalidConfig);
+            }
+            if vesting.is_some_and(|(cliff, duration)| cliff > duration) {
+                return Err(SaleError::InvalidConfig);
+            }
+            self.vesting = vesting;
+
+            Ok(())
+        }
+
+        /// Returns the Dutch auction price schedule, if the sale runs as one
+        #[ink(message)]
+        pub fn dutch_auction(&self) -> Option<DutchAuction> {
+            self.dutch_auction
+        }
+
+        /// Returns the current Dutch auction price in native currency per
+        /// `unit` tokens, or `None` for fixed-rate sales
+        #[ink(message)]
+        pub fn current_price(&self) -> Option<U256> {
+            let auction = self.dutch_auction?;
+
+            let now = self
+                .env()
+                .block_timestamp()
+                .clamp(self.opening_time, self.closing_time);
+            let elapsed = U256::from(now - self.opening_time);
+            let window = U256::from(self.closing_time - self.opening_time);
+            let decay = (auction.start_price - auction.floor_price) * elapsed / window;
+
+            Some(auction.start_price - decay)
+        }
+
+        /// Run the sale as a Dutch auction, or at the fixed rate with `None`
+        ///
+        /// Only possible before the sale opens (owner only).
+        #[ink(message)]
+        pub fn set_dutch_auction(&mut self, auction: Option<DutchAuction>) -> Result<(), SaleError> {
+            self.ensure_owner()?;
+
+            if self.env().block_timestamp() >= self.opening_time {
+                return Err(SaleError::InvalidConfig);
+            }
+            if auction.is_some_and(|auction| {
+                auction.floor_price.is_zero()
+                    || auction.start_price < auction.floor_price
+                    || auction.unit == 0
+                    || auction.supply == 0
+            }) {
+                return Err(SaleError::InvalidConfig);
+            }
+            self.dutch_auction = auction;
+
+            Ok(())
+        }
+
+        /// Send the caller's vested but unreleased tokens
+        #[ink(message)]
+        pub fn release_vested(&mut self) -> Result<u128, SaleError> {
+            let buyer = self.env().caller();
+
+            let mut schedule = self.vesting_schedule(buyer);
+            let amount = self.vested_amount(buyer).saturating_sub(schedule.released);
+            if amount == 0 {
+                return Err(SaleError::NothingToRelease);
+            }
+
+            // Record the release before paying out
+            schedule.released += amount;
+            self.vesting_schedules.insert(buyer, &schedule);
+            self.vesting_locked -= amount;
+
+            let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+            token.transfer(buyer, amount, Vec::new())?;
+
+            self.env().emit_event(VestedTokensReleased { buyer, amount });
+
+            Ok(amount)
+        }
+    }
+
+    impl Crowdsale {
+        /// Fails unless the caller is the sale owner
+        fn ensure_owner(&self) -> Result<(), SaleError> {
+            if self.env().caller() != self.owner {
+                return Err(SaleError::Unauthorized);
+            }
+            Ok(())
+        }
+
+        /// Rate and contribution limit applying to `buyer`
+        fn terms_for(&self, buyer: Address) -> Result<(u128, U256), SaleError> {
+            if !self.tiers_enabled {
+                return Ok((self.rate, self.max_contribution));
+            }
+
+            self.buyer_tiers
+                .get(buyer)
+                .and_then(|tier_id| self.tiers.get(tier_id))
+                .map(|tier| (tier.rate, tier.max_contribution))
+                .ok_or(SaleError::NotAllowlisted)
+        }
+
+        /// Tokens bought for `paid` at a fixed `rate`
+        fn fixed_rate_purchase(rate: u128, paid: U256) -> Result<u128, SaleError> {
+            paid.checked_mul(U256::from(rate))
+                .filter(|amount| *amount <= U256::from(u128::MAX))
+                .map(|amount| amount.low_u128())
+                .ok_or(SaleError::Overflow)
+        }
+
+        /// Tokens bought for `paid` at the current auction price and their
+        /// cost, capped by the remaining supply
+        fn dutch_purchase(&self, auction: &DutchAuction, paid: U256) -> Result<(u128, U256), SaleError> {
+            let price = self.current_price().ok_or(SaleError::InvalidConfig)?;
+            let unit = U256::from(auction.unit);
+            let remaining = auction.supply.saturating_sub(self.tokens_sold);
+
+            let amount = paid.checked_mul(unit).ok_or(SaleError::Overflow)? / price;
+            if amount < U256::from(remaining) {
+                if amount.is_zero() {
+                    return Err(SaleError::ZeroPurchase);
+                }
+                return Ok((amount.low_u128(), paid));
+            }
+
+            // Sold out: charge for the rest of the supply, rounding up
+            let (quotient, remainder) = (U256::from(remaining) * price).div_mod(unit);
+            let cost = if remainder.is_zero() { quotient } else { quotient + 1 };
+            Ok((remaining, cost.min(paid)))
+        }
+
+        /// Keep `amount` purchased tokens in the sale and add them to `buyer`'s vesting
+        fn lock_for_vesting(&mut self, buyer: Address, amount: u128) -> Result<(), SaleError> {
+            let this = self.env().address();
+            let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+
+            let vesting_locked = self
+                .vesting_locked
+                .checked_add(amount)
+                .ok_or(SaleError::Overflow)?;
+            if self.mint_on_purchase {
+                token.mint_to(this, amount)?;
+            } else if token.balance_of(this) < vesting_locked {
+                return Err(SaleError::Token(psp_coin::PSP22Error::InsufficientBalance));
+            }
+
+            let mut schedule = self.vesting_schedule(buyer);
+            schedule.total = schedule
+                .total
+                .checked_add(amount)
+                .ok_or(SaleError::Overflow)?;
+            self.vesting_schedules.insert(buyer, &schedule);
+            self.vesting_locked = vesting_locked;
+
+            Ok(())
+        }
+
+        /// Mint or transfer `amount` tokens to `buyer`
+        fn deliver(&self, buyer: Address, amount: u128) -> Result<(), SaleError> {
+            let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+            if self.mint_on_purchase {
+                token.mint_to(buyer, amount)?;
+            } else {
+                token.transfer(buyer, amount, Vec::new())?;
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn set_now(timestamp: Timestamp) {
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(timestamp);
+        }
+
+        #[ink::test]
+        fn new_rejects_invalid_config() {
+            let accounts = ink::env::test::default_accounts();
+
+            assert_eq!(
+                Crowdsale::new(accounts.django, 0, U256::from(100), U256::from(10), 0, 10, true).err(),
+                Some(SaleError::InvalidConfig)
+            );
+            assert_eq!(
+                Crowdsale::new(accounts.django, 1, U256::from(100), U256::from(10), 10, 10, true).err(),
+                Some(SaleError::InvalidConfig)
+            );
+        }
+
+        #[ink::test]
+        fn buy_enforces_window_and_limits() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut sale =
+                Crowdsale::new(accounts.django, 2, U256::from(100), U256::from(10), 10, 20, true)
+                    .expect("valid config");
+
+            ink::env::test::set_value_transferred(U256::from(5));
+            assert_eq!(sale.buy(), Err(SaleError::NotOpen));
+
+            set_now(10);
+            assert!(sale.is_open());
+            ink::env::test::set_value_transferred(U256::from(11));
+            assert_eq!(sale.buy(), Err(SaleError::ContributionLimitExceeded));
+            ink::env::test::set_value_transferred(U256::zero());
+            assert_eq!(sale.buy(), Err(SaleError::ZeroPurchase));
+
+            set_now(20);
+            assert!(!sale.is_open());
+        }
+
+        #[ink::test]
+        fn withdrawals_are_owner_only() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut sale =
+                Crowdsale::new(accounts.django, 2, U256::from(100), U256::from(10), 10, 20, false)
+                    .expect("valid config");
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(sale.withdraw_proceeds(accounts.bob), Err(SaleError::Unauthorized));
+            assert_eq!(sale.withdraw_unsold(accounts.bob), Err(SaleError::Unauthorized));
+
+            ink::env::test::set_caller(accounts.alice);
+            assert_eq!(sale.withdraw_unsold(accounts.alice), Err(SaleError::NotClosed));
+        }
+
+        #[ink::test]
+        fn tiers_restrict_buyers_and_set_terms() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut sale =
+                Crowdsale::new(accounts.django, 2, U256::from(100), U256::from(10), 10, 20, true)
+                    .expect("valid config");
+            assert_eq!(sale.set_tier(1, 4, U256::from(50)), Ok(()));
+            assert_eq!(sale.assign_tier(vec![accounts.bob], Some(1)), Ok(()));
+            assert_eq!(
+                sale.assign_tier(vec![accounts.bob], Some(2)),
+                Err(SaleError::InvalidConfig)
+            );
+            assert_eq!(sale.set_tiers_enabled(true), Ok(()));
+            assert_eq!(sale.tier_of(accounts.bob), Some(1));
+
+            set_now(10);
+            ink::env::test::set_value_transferred(U256::from(5));
+            assert_eq!(sale.buy(), Err(SaleError::NotAllowlisted));
+
+            ink::env::test::set_caller(accounts.bob);
+            ink::env::test::set_value_transferred(U256::from(51));
+            assert_eq!(sale.buy(), Err(SaleError::ContributionLimitExceeded));
+        }
+
+        #[ink::test]
+        fn dutch_auction_price_decays_to_floor() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut sale =
+                Crowdsale::new(accounts.django, 1, U256::from(10_000), U256::from(10_000), 10, 110, true)
+                    .expect("valid config");
+            assert_eq!(sale.current_price(), None);
+
+            let auction = DutchAuction {
+                start_price: U256::from(300),
+                floor_price: U256::from(100),
+                unit: 1_000,
+                supply: 10_000,
+            };
+            assert_eq!(sale.set_dutch_auction(Some(auction)), Ok(()));
+
+            assert_eq!(sale.current_price(), Some(U256::from(300)));
+            set_now(60);
+            assert_eq!(sale.current_price(), Some(U256::from(200)));
+            set_now(500);
+            assert_eq!(sale.current_price(), Some(U256::from(100)));
+
+            // The sale closes as soon as the supply is sold
+            set_now(60);
+            sale.tokens_sold = 10_000;
+            assert!(!sale.is_open());
+        }
+
+        #[ink::test]
+        fn vesting_releases_linearly_after_close() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut sale =
+                Crowdsale::new(accounts.django, 2, U256::from(100), U256::from(10), 10, 20, true)
+                    .expect("valid config");
+            assert_eq!(sale.set_vesting(Some((50, 100))), Ok(()));
+            sale.vesting_schedules.insert(
+                accounts.bob,
+                &VestingSchedule {
+                    total: 1000,
+                    released: 0,
+                },
+            );
+
+            set_now(69);
+            assert_eq!(sale.vested_amount(accounts.bob), 0);
+            set_now(70);
+            assert_eq!(sale.vested_amount(accounts.bob), 500);
+            set_now(120);
+            assert_eq!(sale.vested_amount(accounts.bob), 1000);
+
+            // Vesting can no longer change once the sale opened
+            assert_eq!(sale.set_vesting(None), Err(SaleError::InvalidConfig));
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::PspCoin;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn buy_mints_tokens_to_buyer<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given an open sale that owns the token
+            let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+            let mut token_constructor = PspCoinRef::new();
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut sale_constructor = CrowdsaleRef::new(
+                token.addr,
+                10,
+                U256::from(1_000_000),
+                U256::from(10_000),
+                0,
+                u64::MAX,
+                true,
+            );
+            let sale = client
+                .instantiate("crowdsale", &ink_e2e::alice(), &mut sale_constructor)
+                .submit()
+                .await
+                .expect("sale instantiate failed");
+            let mut sale_call = sale.call_builder::<Crowdsale>();
+
+            client
+                .call(&ink_e2e::alice(), &token_call.transfer_ownership(sale.addr))
+                .submit()
+                .await?;
+
+            // When bob buys with 1000 units of native currency
+            client
+                .call(&ink_e2e::bob(), &sale_call.buy())
+                .value(1_000)
+                .submit()
+                .await?;
+
+            // Then bob received tokens at the configured rate
+            let balance = client
+                .call(&ink_e2e::bob(), &token_call.balance_of(bob))
+                .dry_run()
+                .await?;
+            assert_eq!(balance.return_value(), 10_000);
+
+            let contribution = client
+                .call(&ink_e2e::bob(), &sale_call.contribution_of(bob))
+                .dry_run()
+                .await?;
+            assert_eq!(contribution.return_value(), U256::from(1_000));
+
+            Ok(())
+        }
+    }
+}
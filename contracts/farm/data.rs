@@ -0,0 +1,29 @@
+use ink::Address;
+
+/// A staking pool of one LP token
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct PoolInfo {
+    /// PSP22 token staked in the pool
+    pub lp_token: Address,
+    /// Weight of the pool in the reward emission
+    pub alloc_point: u32,
+    /// Block up to which rewards have been emitted to the pool
+    pub last_reward_block: u32,
+    /// Rewards emitted per staked token, scaled by `ACC_PRECISION`
+    pub acc_reward_per_share: u128,
+    /// Sum of all LP tokens staked in the pool
+    pub total_staked: u128,
+}
+
+/// Stake of one account in a pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct UserInfo {
+    /// LP tokens staked by the account
+    pub amount: u128,
+    /// Part of the account's accumulated rewards already accounted for
+    pub reward_debt: u128,
+}
@@ -0,0 +1,420 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+mod data;
+
+pub use data::{PoolInfo, UserInfo};
+pub use self::farm::FarmRef;
+
+#[ink::contract]
+mod farm {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use ink::env::call::FromAddr;
+    use psp_coin::{PSP22Error, PspCoinRef};
+
+    use crate::data::{PoolInfo, UserInfo};
+
+    /// Scaling factor for the per-share reward accumulators
+    const ACC_PRECISION: u128 = 1_000_000_000_000;
+
+    /// Storage structure for the liquidity mining farm
+    ///
+    /// Rewards are paid in `reward_token` from the farm's own balance, which
+    /// the owner funds with plain transfers. Every block `reward_per_block`
+    /// is split across pools by their allocation points and within a pool
+    /// by stake.
+    #[ink(storage)]
+    pub struct Farm {
+        /// Account allowed to manage pools and the emission rate
+        owner: Address,
+        /// Token paid out as rewards
+        reward_token: Address,
+        /// Rewards emitted per block across all pools
+        reward_per_block: u128,
+        /// Sum of the allocation points of all pools
+        total_alloc_point: u32,
+        /// Pools by id
+        pools: Vec<PoolInfo>,
+        /// Stakes by (pool id, account)
+        users: Mapping<(u32, Address), UserInfo>,
+    }
+
+    /// Event emitted when LP tokens are staked
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        pub account: Address,
+        #[ink(topic)]
+        pub pool_id: u32,
+        pub amount: u128,
+    }
+
+    /// Event emitted when LP tokens are unstaked
+    #[ink(event)]
+    pub struct Withdraw {
+        #[ink(topic)]
+        pub account: Address,
+        #[ink(topic)]
+        pub pool_id: u32,
+        pub amount: u128,
+    }
+
+    /// Event emitted when LP tokens are unstaked without claiming rewards
+    #[ink(event)]
+    pub struct EmergencyWithdraw {
+        #[ink(topic)]
+        pub account: Address,
+        #[ink(topic)]
+        pub pool_id: u32,
+        pub amount: u128,
+    }
+
+    /// Event emitted when rewards are paid out
+    #[ink(event)]
+    pub struct Harvest {
+        #[ink(topic)]
+        pub account: Address,
+        #[ink(topic)]
+        pub pool_id: u32,
+        pub amount: u128,
+    }
+
+    impl Farm {
+        /// Constructor that emits `reward_per_block` of `reward_token`
+        #[ink(constructor)]
+        pub fn new(reward_token: Address, reward_per_block: u128) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                reward_token,
+                reward_per_block,
+                total_alloc_point: 0,
+                pools: Vec::new(),
+                users: Mapping::default(),
+            }
+        }
+    }
+
+    impl Farm {
+        /// Returns the farm owner
+        #[ink(message)]
+        pub fn owner(&self) -> Address {
+            self.owner
+        }
+
+        /// Returns the token paid out as rewards
+        #[ink(message)]
+        pub fn reward_token(&self) -> Address {
+            self.reward_token
+        }
+
+        /// Returns the rewards emitted per block across all pools
+        #[ink(message)]
+        pub fn reward_per_block(&self) -> u128 {
+            self.reward_per_block
+        }
+
+        /// Returns the number of pools
+        #[ink(message)]
+        pub fn pool_count(&self) -> u32 {
+            self.pools.len() as u32
+        }
+
+        /// Returns the pool with the given id
+        #[ink(message)]
+        pub fn pool(&self, pool_id: u32) -> Option<PoolInfo> {
+            self.pools.get(pool_id as usize).cloned()
+        }
+
+        /// Returns the stake of `account` in a pool
+        #[ink(message)]
+        pub fn user_info(&self, pool_id: u32, account: Address) -> UserInfo {
+            self.users.get((pool_id, account)).unwrap_or_default()
+        }
+
+        /// Returns the rewards `account` can currently harvest from a pool
+        #[ink(message)]
+        pub fn pending_reward(&self, pool_id: u32, account: Address) -> u128 {
+            let Some(pool) = self.pools.get(pool_id as usize) else {
+                return 0;
+            };
+            let user = self.user_info(pool_id, account);
+
+            let acc_reward_per_share = self.projected_acc_reward_per_share(pool);
+            (user.amount.saturating_mul(acc_reward_per_share) / ACC_PRECISION)
+                .saturating_sub(user.reward_debt)
+        }
+
+        /// Add a pool for `lp_token` with the given weight (owner only)
+        #[ink(message)]
+        pub fn add_pool(&mut self, lp_token: Address, alloc_point: u32) -> Result<u32, PSP22Error> {
+            self.ensure_owner()?;
+            if self.pools.iter().any(|pool| pool.lp_token == lp_token) {
+                return Err(PSP22Error::Custom(String::from("Pool exists")));
+            }
+
+            self.update_all_pools();
+            self.total_alloc_point = self
+                .total_alloc_point
+                .checked_add(alloc_point)
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+
+            self.pools.push(PoolInfo {
+                lp_token,
+                alloc_point,
+                last_reward_block: self.env().block_number(),
+                acc_reward_per_share: 0,
+                total_staked: 0,
+            });
+
+            Ok(self.pool_count() - 1)
+        }
+
+        /// Change the weight of a pool (owner only)
+        #[ink(message)]
+        pub fn set_pool(&mut self, pool_id: u32, alloc_point: u32) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            self.existing_pool(pool_id)?;
+
+            self.update_all_pools();
+            let pool = &mut self.pools[pool_id as usize];
+            self.total_alloc_point = self.total_alloc_point - pool.alloc_point + alloc_point;
+            pool.alloc_point = alloc_point;
+
+            Ok(())
+        }
+
+        /// Change the rewards emitted per block (owner only)
+        #[ink(message)]
+        pub fn set_reward_per_block(&mut self, reward_per_block: u128) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+
+            // Emit everything owed at the old rate first
+            self.update_all_pools();
+            self.reward_per_block = reward_per_block;
+
+            Ok(())
+        }
+
+        /// Stake `amount` LP tokens in a pool, harvesting pending rewards
+        ///
+        /// The caller must have approved the farm to spend `amount`. A
+        /// deposit of zero only harvests.
+        #[ink(message)]
+        pub fn deposit(&mut self, pool_id: u32, amount: u128) -> Result<(), PSP22Error> {
+            let account = self.env().caller();
+            let this = self.env().address();
+            self.existing_pool(pool_id)?;
+
+            self.update_pool(pool_id);
+            let mut user = self.user_info(pool_id, account);
+            self.harvest_pending(pool_id, account, &user)?;
+
+            if amount > 0 {
+                let pool = &self.pools[pool_id as usize];
+                let mut lp_token: PspCoinRef = FromAddr::from_addr(pool.lp_token);
+                lp_token.transfer_from(account, this, amount, Vec::new())?;
+
+                user.amount = user
+                    .amount
+                    .checked_add(amount)
+                    .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+                self.pools[pool_id as usize].total_staked += amount;
+            }
+            self.store_user(pool_id, account, user);
+
+            self.env().emit_event(Deposit {
+                account,
+                pool_id,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Unstake `amount` LP tokens from a pool, harvesting pending rewards
+        #[ink(message)]
+        pub fn withdraw(&mut self, pool_id: u32, amount: u128) -> Result<(), PSP22Error> {
+            let account = self.env().caller();
+            self.existing_pool(pool_id)?;
+
+            let mut user = self.user_info(pool_id, account);
+            if user.amount < amount {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+
+            self.update_pool(pool_id);
+            self.harvest_pending(pool_id, account, &user)?;
+
+            user.amount -= amount;
+            self.pools[pool_id as usize].total_staked -= amount;
+            self.store_user(pool_id, account, user);
+
+            if amount > 0 {
+                let mut lp_token: PspCoinRef = FromAddr::from_addr(self.pools[pool_id as usize].lp_token);
+                lp_token.transfer(account, amount, Vec::new())?;
+            }
+
+            self.env().emit_event(Withdraw {
+                account,
+                pool_id,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Unstake all LP tokens from a pool, forfeiting pending rewards
+        ///
+        /// Works even if the farm cannot pay rewards.
+        #[ink(message)]
+        pub fn emergency_withdraw(&mut self, pool_id: u32) -> Result<(), PSP22Error> {
+            let account = self.env().caller();
+            self.existing_pool(pool_id)?;
+
+            let amount = self.user_info(pool_id, account).amount;
+            self.users.remove((pool_id, account));
+            self.pools[pool_id as usize].total_staked -= amount;
+
+            let mut lp_token: PspCoinRef = FromAddr::from_addr(self.pools[pool_id as usize].lp_token);
+            lp_token.transfer(account, amount, Vec::new())?;
+
+            self.env().emit_event(EmergencyWithdraw {
+                account,
+                pool_id,
+                amount,
+            });
+
+            Ok(())
+        }
+    }
+
+    impl Farm {
+        /// Fails unless the caller is the farm owner
+        fn ensure_owner(&self) -> Result<(), PSP22Error> {
+            if self.env().caller() != self.owner {
+                return Err(PSP22Error::Unauthorized);
+            }
+            Ok(())
+        }
+
+        /// Fails unless a pool with the given id exists
+        fn existing_pool(&self, pool_id: u32) -> Result<(), PSP22Error> {
+            if pool_id as usize >= self.pools.len() {
+                return Err(PSP22Error::Custom(String::from("Unknown pool")));
+            }
+            Ok(())
+        }
+
+        /// Accumulator of `pool` as it would be after emitting up to now
+        fn projected_acc_reward_per_share(&self, pool: &PoolInfo) -> u128 {
+            let now = self.env().block_number();
+            if pool.total_staked == 0 || now <= pool.last_reward_block || self.total_alloc_point == 0 {
+                return pool.acc_reward_per_share;
+            }
+
+            let blocks = u128::from(now - pool.last_reward_block);
+            let reward = blocks
+                .saturating_mul(self.reward_per_block)
+                .saturating_mul(u128::from(pool.alloc_point))
+                / u128::from(self.total_alloc_point);
+
+            pool.acc_reward_per_share
+                .saturating_add(reward.saturating_mul(ACC_PRECISION) / pool.total_staked)
+        }
+
+        /// Emit rewards accrued since the last update into a pool's accumulator
+        fn update_pool(&mut self, pool_id: u32) {
+            let acc_reward_per_share = self.projected_acc_reward_per_share(&self.pools[pool_id as usize]);
+            let now = self.env().block_number();
+
+            let pool = &mut self.pools[pool_id as usize];
+            pool.acc_reward_per_share = acc_reward_per_share;
+            pool.last_reward_block = pool.last_reward_block.max(now);
+        }
+
+        /// Update the accumulators of all pools
+        fn update_all_pools(&mut self) {
+            for pool_id in 0..self.pool_count() {
+                self.update_pool(pool_id);
+            }
+        }
+
+        /// Pay out the rewards `user` earned in an updated pool
+        fn harvest_pending(&mut self, pool_id: u32, account: Address, user: &UserInfo) -> Result<(), PSP22Error> {
+            let acc_reward_per_share = self.pools[pool_id as usize].acc_reward_per_share;
+            let pending = (user.amount.saturating_mul(acc_reward_per_share) / ACC_PRECISION)
+                .saturating_sub(user.reward_debt);
+            if pending == 0 {
+                return Ok(());
+            }
+
+            let mut reward_token: PspCoinRef = FromAddr::from_addr(self.reward_token);
+            reward_token.transfer(account, pending, Vec::new())?;
+
+            self.env().emit_event(Harvest {
+                account,
+                pool_id,
+                amount: pending,
+            });
+
+            Ok(())
+        }
+
+        /// Store a stake with all rewards up to now accounted for
+        fn store_user(&mut self, pool_id: u32, account: Address, mut user: UserInfo) {
+            let acc_reward_per_share = self.pools[pool_id as usize].acc_reward_per_share;
+            user.reward_debt = user.amount.saturating_mul(acc_reward_per_share) / ACC_PRECISION;
+            self.users.insert((pool_id, account), &user);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn add_pool_is_owner_only() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut farm = Farm::new(accounts.django, 100);
+            assert_eq!(farm.add_pool(accounts.eve, 1), Ok(0));
+            assert_eq!(
+                farm.add_pool(accounts.eve, 1),
+                Err(PSP22Error::Custom(String::from("Pool exists")))
+            );
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(farm.add_pool(accounts.frank, 1), Err(PSP22Error::Unauthorized));
+            assert_eq!(farm.set_reward_per_block(0), Err(PSP22Error::Unauthorized));
+        }
+
+        #[ink::test]
+        fn rewards_are_split_by_alloc_points_and_stake() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut farm = Farm::new(accounts.django, 100);
+            assert_eq!(farm.add_pool(accounts.eve, 1), Ok(0));
+            assert_eq!(farm.add_pool(accounts.frank, 3), Ok(1));
+
+            // Bob and Charlie stake 1:3 in the first pool, Bob alone in the second
+            for (pool_id, account, amount) in [
+                (0, accounts.bob, 100),
+                (0, accounts.charlie, 300),
+                (1, accounts.bob, 50),
+            ] {
+                farm.store_user(pool_id, account, UserInfo { amount, reward_debt: 0 });
+                farm.pools[pool_id as usize].total_staked += amount;
+            }
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            // 200 emitted: 50 to the first pool, 150 to the second
+            assert_eq!(farm.pending_reward(0, accounts.bob), 12);
+            assert_eq!(farm.pending_reward(0, accounts.charlie), 37);
+            assert_eq!(farm.pending_reward(1, accounts.bob), 150);
+            assert_eq!(farm.pending_reward(2, accounts.bob), 0);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::flash_borrower::FlashBorrowerRef;
+
+#[ink::contract]
+mod flash_borrower {
+    use ink::env::{
+        call::{build_call, ExecutionInput, Selector},
+        DefaultEnvironment,
+    };
+    use ink::prelude::vec::Vec;
+    use psp22_interface::{PSP22Error, PSP22Ref};
+
+    /// Selector of the token's `flash_loan(receiver, amount, data)`
+    const FLASH_LOAN_SELECTOR: [u8; 4] = [0x90, 0x25, 0x7a, 0x58];
+
+    /// Error code returned when a callback does not come from the token
+    const NOT_TOKEN: u32 = 1;
+
+    /// Error code returned when approving the repayment fails
+    const APPROVE_FAILED: u32 = 2;
+
+    /// Storage structure for the flash borrower
+    ///
+    /// A flash loan receiver for testing the token's `flash_loan`: on every
+    /// callback it approves the token to take back the loan plus fee, the
+    /// way an EIP-3156 borrower repays. With `reenter` set it first tries a
+    /// nested flash loan and records the error the token returned.
+    #[ink(storage)]
+    pub struct FlashBorrower {
+        /// Token the borrower accepts loans from
+        token: Address,
+        /// Whether each callback tries a nested flash loan first
+        reenter: bool,
+        /// Number of loans received
+        loans: u32,
+        /// Error code returned by the last nested flash loan, if it failed
+        last_reentry_error: Option<u32>,
+    }
+
+    impl FlashBorrower {
+        /// Constructor accepting loans from `token`
+        #[ink(constructor)]
+        pub fn new(token: Address, reenter: bool) -> Self {
+            Self {
+                token,
+                reenter,
+                loans: 0,
+                last_reentry_error: None,
+            }
+        }
+    }
+
+    impl FlashBorrower {
+        /// Returns the number of loans received
+        #[ink(message)]
+        pub fn loans(&self) -> u32 {
+            self.loans
+        }
+
+        /// Returns the error code of the last nested flash loan, if it failed
+        #[ink(message)]
+        pub fn last_reentry_error(&self) -> Option<u32> {
+            self.last_reentry_error
+        }
+
+        /// Callback invoked by the token's `flash_loan`; approves the token
+        /// to burn back `amount` plus `fee`
+        #[ink(message, selector = 0x08481E7F)]
+        pub fn on_flash_loan(
+            &mut self,
+            _initiator: Address,
+            amount: u128,
+            fee: u128,
+            _data: Vec<u8>,
+        ) -> Result<(), u32> {
+            if self.env().caller() != self.token {
+                return Err(NOT_TOKEN);
+            }
+            self.loans = self.loans.saturating_add(1);
+
+            if self.reenter {
+                let nested = build_call::<DefaultEnvironment>()
+                    .call(self.token)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(FLASH_LOAN_SELECTOR))
+                            .push_arg(self.env().address())
+                            .push_arg(1u128)
+                            .push_arg(Vec::<u8>::new()),
+                    )
+                    .returns::<Result<(), PSP22Error>>()
+                    .try_invoke();
+                self.last_reentry_error = match nested {
+                    Ok(Ok(Err(error))) => Some(u32::from(error)),
+                    _ => None,
+                };
+            }
+
+            let mut token: PSP22Ref = self.token.into();
+            token
+                .approve(self.token, amount.saturating_add(fee))
+                .map_err(|_| APPROVE_FAILED)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn callbacks_from_other_callers_are_rejected() {
+            let accounts = ink::env::test::default_accounts();
+            let mut borrower = FlashBorrower::new(accounts.django, false);
+
+            ink::env::test::set_caller(accounts.alice);
+            assert_eq!(
+                borrower.on_flash_loan(accounts.alice, 100, 1, Vec::new()),
+                Err(NOT_TOKEN)
+            );
+            assert_eq!(borrower.loans(), 0);
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::{PspCoin, PspCoinRef};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn flash_loan_is_repaid_through_callback_approval<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a token charging a 1% flash fee and a borrower holding 100
+            let mut token_constructor = PspCoinRef::new_with_supply(1_000);
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut borrower_constructor = FlashBorrowerRef::new(token.addr, false);
+            let borrower = client
+                .instantiate("flash_borrower", &ink_e2e::alice(), &mut borrower_constructor)
+                .submit()
+                .await
+                .expect("borrower instantiate failed");
+            let borrower_call = borrower.call_builder::<FlashBorrower>();
+
+            client
+                .call(&ink_e2e::alice(), &token_call.set_flash_fee(100))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::alice(), &token_call.transfer(borrower.addr, 100, Vec::new()))
+                .submit()
+                .await?;
+
+            // When alice flash lends it 1000 tokens
+            let loan = client
+                .call(&ink_e2e::alice(), &token_call.flash_loan(borrower.addr, 1_000, Vec::new()))
+                .submit()
+                .await?
+                .return_value();
+            assert_eq!(loan, Ok(()));
+
+            // Then the loan and the 10 token fee were burned back
+            let loans = client
+                .call(&ink_e2e::alice(), &borrower_call.loans())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(loans, 1);
+
+            let balance = client
+                .call(&ink_e2e::alice(), &token_call.balance_of(borrower.addr))
+                .dry_run()
+                .await?;
+            assert_eq!(balance.return_value(), 90);
+            let supply = client
+                .call(&ink_e2e::alice(), &token_call.total_supply())
+                .dry_run()
+                .await?;
+            assert_eq!(supply.return_value(), 990);
+
+            // Repaying the loan is not a burn of the borrower's own choosing
+            let burned = client
+                .call(&ink_e2e::alice(), &token_call.burned_of(borrower.addr))
+                .dry_run()
+                .await?;
+            assert_eq!(burned.return_value(), 0);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn nested_flash_loan_is_rejected_by_the_guard<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a borrower that tries a nested flash loan on every callback
+            let mut token_constructor = PspCoinRef::new_with_supply(1_000);
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut borrower_constructor = FlashBorrowerRef::new(token.addr, true);
+            let borrower = client
+                .instantiate("flash_borrower", &ink_e2e::alice(), &mut borrower_constructor)
+                .submit()
+                .await
+                .expect("borrower instantiate failed");
+            let borrower_call = borrower.call_builder::<FlashBorrower>();
+
+            // When alice flash lends it 1000 tokens
+            let loan = client
+                .call(&ink_e2e::alice(), &token_call.flash_loan(borrower.addr, 1_000, Vec::new()))
+                .submit()
+                .await?
+                .return_value();
+            assert_eq!(loan, Ok(()));
+
+            // Then the outer loan went through while the nested one hit the guard
+            let error = client
+                .call(&ink_e2e::alice(), &borrower_call.last_reentry_error())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(error, Some(u32::from(PSP22Error::ReentrancyDetected)));
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use ink::prelude::vec::Vec;
+use ink::Address;
+
+/// Governor error types
+#[derive(Debug, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum GovernorError {
+    /// No proposal exists with the given id
+    ProposalNotFound,
+    /// Proposal is not in the state required for this action
+    InvalidProposalState,
+    /// Voter already voted on this proposal
+    AlreadyVoted,
+    /// Reading vote weight from the token failed
+    VotesUnavailable,
+    /// The proposed call failed
+    ExecutionFailed,
+    /// Arithmetic overflow
+    Overflow,
+}
+
+/// Possible votes on a proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub enum VoteType {
+    Against,
+    For,
+    Abstain,
+}
+
+/// Lifecycle of a proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum ProposalState {
+    /// Voting has not started yet
+    Pending,
+    /// Votes are being cast
+    Active,
+    /// Voting ended without reaching quorum or majority
+    Defeated,
+    /// Voting ended successfully, the call can be executed
+    Succeeded,
+    /// The call has been executed
+    Executed,
+}
+
+/// A cross-contract call voted on by token holders
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Proposal {
+    /// Account that created the proposal
+    pub proposer: Address,
+    /// Contract to call on execution
+    pub callee: Address,
+    /// Selector of the message to call
+    pub selector: [u8; 4],
+    /// SCALE encoded message arguments
+    pub input: Vec<u8>,
+    /// Block at which vote weights are read
+    pub vote_start: u32,
+    /// Last block in which votes are accepted
+    pub vote_end: u32,
+    /// Votes in favour
+    pub for_votes: u128,
+    /// Votes against
+    pub against_votes: u128,
+    /// Abstaining votes
+    pub abstain_votes: u128,
+    /// Whether the call has been executed
+    pub executed: bool,
+}
@@ -0,0 +1,341 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+mod data;
+
+pub use data::{GovernorError, Proposal, ProposalState, VoteType};
+pub use self::governor::GovernorRef;
+
+#[ink::contract]
+mod governor {
+    use ink::{storage::Mapping, prelude::vec::Vec};
+    use ink::env::{
+        call::{build_call, ExecutionInput, FromAddr, Selector},
+        DefaultEnvironment,
+    };
+    use ink::scale::{Decode, Encode, Input, Output};
+    use psp_coin::PspCoinRef;
+
+    use crate::data::{GovernorError, Proposal, ProposalState, VoteType};
+
+    /// Raw, already SCALE encoded call arguments
+    struct CallInput<'a>(&'a [u8]);
+
+    impl Encode for CallInput<'_> {
+        fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(self.0);
+        }
+    }
+
+    /// Return data of an executed call, consumed without being decoded
+    struct CallOutput;
+
+    impl Decode for CallOutput {
+        fn decode<I: Input>(input: &mut I) -> Result<Self, ink::scale::Error> {
+            while input.read_byte().is_ok() {}
+            Ok(CallOutput)
+        }
+    }
+
+    /// Storage structure for the governor
+    #[ink(storage)]
+    pub struct Governor {
+        /// Token whose checkpointed votes are used as vote weight
+        token: Address,
+        /// Blocks between proposal creation and the start of voting
+        voting_delay: BlockNumber,
+        /// Blocks during which votes are accepted
+        voting_period: BlockNumber,
+        /// Minimum for + abstain votes for a proposal to pass
+        quorum: u128,
+        /// Number of proposals created so far
+        proposal_count: u32,
+        /// Proposals by id
+        proposals: Mapping<u32, Proposal>,
+        /// Accounts that voted on each proposal
+        has_voted: Mapping<(u32, Address), ()>,
+    }
+
+    /// Event emitted when a proposal is created
+    #[ink(event)]
+    pub struct ProposalCreated {
+        #[ink(topic)]
+        pub proposal_id: u32,
+        #[ink(topic)]
+        pub proposer: Address,
+        pub vote_start: BlockNumber,
+        pub vote_end: BlockNumber,
+    }
+
+    /// Event emitted when a vote is cast
+    #[ink(event)]
+    pub struct VoteCast {
+        #[ink(topic)]
+        pub voter: Address,
+        #[ink(topic)]
+        pub proposal_id: u32,
+        pub support: VoteType,
+        pub weight: u128,
+    }
+
+    /// Event emitted when a proposal is executed
+    #[ink(event)]
+    pub struct ProposalExecuted {
+        #[ink(topic)]
+        pub proposal_id: u32,
+    }
+
+    impl Governor {
+        /// Constructor that binds the governor to a votes token
+        #[ink(constructor)]
+        pub fn new(
+            token: Address,
+            voting_delay: BlockNumber,
+            voting_period: BlockNumber,
+            quorum: u128,
+        ) -> Self {
+            Self {
+                token,
+                voting_delay,
+                voting_period,
+                quorum,
+                proposal_count: 0,
+                proposals: Mapping::default(),
+                has_voted: Mapping::default(),
+            }
+        }
+    }
+
+    impl Governor {
+        /// Returns the votes token
+        #[ink(message)]
+        pub fn token(&self) -> Address {
+            self.token
+        }
+
+        /// Returns the voting delay, voting period and quorum
+        #[ink(message)]
+        pub fn settings(&self) -> (BlockNumber, BlockNumber, u128) {
+            (self.voting_delay, self.voting_period, self.quorum)
+        }
+
+        /// Returns the proposal with the given id
+        #[ink(message)]
+        pub fn proposal(&self, proposal_id: u32) -> Option<Proposal> {
+            self.proposals.get(proposal_id)
+        }
+
+        /// Returns whether `voter` voted on the proposal
+        #[ink(message)]
+        pub fn has_voted(&self, proposal_id: u32, voter: Address) -> bool {
+            self.has_voted.contains((proposal_id, voter))
+        }
+
+        /// Returns the current state of the proposal
+        #[ink(message)]
+        pub fn state(&self, proposal_id: u32) -> Result<ProposalState, GovernorError> {
+            let proposal = self
+                .proposals
+                .get(proposal_id)
+                .ok_or(GovernorError::ProposalNotFound)?;
+
+            Ok(self.state_of(&proposal))
+        }
+
+        /// Propose calling `selector` on `callee` with SCALE encoded `input`
+        #[ink(message)]
+        pub fn propose(
+            &mut self,
+            callee: Address,
+            selector: [u8; 4],
+            input: Vec<u8>,
+        ) -> Result<u32, GovernorError> {
+            let proposer = self.env().caller();
+            let now = self.env().block_number();
+
+            let vote_start = now
+                .checked_add(self.voting_delay)
+                .ok_or(GovernorError::Overflow)?;
+            let vote_end = vote_start
+                .checked_add(self.voting_period)
+                .ok_or(GovernorError::Overflow)?;
+
+            let proposal_id = self.proposal_count;
+            self.proposal_count = proposal_id
+                .checked_add(1)
+                .ok_or(GovernorError::Overflow)?;
+
+            self.proposals.insert(
+                proposal_id,
+                &Proposal {
+                    proposer,
+                    callee,
+                    selector,
+                    input,
+                    vote_start,
+                    vote_end,
+                    for_votes: 0,
+                    against_votes: 0,
+                    abstain_votes: 0,
+                    executed: false,
+                },
+            );
+
+            self.env().emit_event(ProposalCreated {
+                proposal_id,
+                proposer,
+                vote_start,
+                vote_end,
+            });
+
+            Ok(proposal_id)
+        }
+
+        /// Cast the caller's vote, weighted by its token votes at `vote_start`
+        #[ink(message)]
+        pub fn cast_vote(&mut self, proposal_id: u32, support: VoteType) -> Result<(), GovernorError> {
+            let voter = self.env().caller();
+            let mut proposal = self
+                .proposals
+                .get(proposal_id)
+                .ok_or(GovernorError::ProposalNotFound)?;
+
+            if self.state_of(&proposal) != ProposalState::Active {
+                return Err(GovernorError::InvalidProposalState);
+            }
+            if self.has_voted.contains((proposal_id, voter)) {
+                return Err(GovernorError::AlreadyVoted);
+            }
+
+            let token: PspCoinRef = FromAddr::from_addr(self.token);
+            let weight = token
+                .get_past_votes(voter, proposal.vote_start)
+                .map_err(|_| GovernorError::VotesUnavailable)?;
+
+            let tally = match support {
+                VoteType::Against => &mut proposal.against_votes,
+                VoteType::For => &mut proposal.for_votes,
+                VoteType::Abstain => &mut proposal.abstain_votes,
+            };
+            *tally = tally.checked_add(weight).ok_or(GovernorError::Overflow)?;
+
+            self.proposals.insert(proposal_id, &proposal);
+            self.has_voted.insert((proposal_id, voter), &());
+
+            self.env().emit_event(VoteCast {
+                voter,
+                proposal_id,
+                support,
+                weight,
+            });
+
+            Ok(())
+        }
+
+        /// Execute the call of a succeeded proposal
+        #[ink(message)]
+        pub fn execute(&mut self, proposal_id: u32) -> Result<(), GovernorError> {
+            let mut proposal = self
+                .proposals
+                .get(proposal_id)
+                .ok_or(GovernorError::ProposalNotFound)?;
+
+            if self.state_of(&proposal) != ProposalState::Succeeded {
+                return Err(GovernorError::InvalidProposalState);
+            }
+
+            // Mark executed before the call so it cannot be executed twice
+            proposal.executed = true;
+            self.proposals.insert(proposal_id, &proposal);
+
+            build_call::<DefaultEnvironment>()
+                .call(proposal.callee)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(proposal.selector))
+                        .push_arg(CallInput(&proposal.input)),
+                )
+                .returns::<CallOutput>()
+                .try_invoke()
+                .map_err(|_| GovernorError::ExecutionFailed)?
+                .map_err(|_| GovernorError::ExecutionFailed)?;
+
+            self.env().emit_event(ProposalExecuted { proposal_id });
+
+            Ok(())
+        }
+    }
+
+    impl Governor {
+        /// Derive the state of a proposal from the current block and tallies
+        fn state_of(&self, proposal: &Proposal) -> ProposalState {
+            let now = self.env().block_number();
+
+            if proposal.executed {
+                ProposalState::Executed
+            } else if now <= proposal.vote_start {
+                ProposalState::Pending
+            } else if now <= proposal.vote_end {
+                ProposalState::Active
+            } else if proposal.for_votes.saturating_add(proposal.abstain_votes) >= self.quorum
+                && proposal.for_votes > proposal.against_votes
+            {
+                ProposalState::Succeeded
+            } else {
+                ProposalState::Defeated
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn propose_works() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut governor = Governor::new(accounts.django, 1, 10, 100);
+
+            assert_eq!(governor.propose(accounts.django, [0; 4], vec![]), Ok(0));
+            let proposal = governor.proposal(0).expect("proposal exists");
+            assert_eq!(proposal.proposer, accounts.alice);
+            assert_eq!(proposal.vote_start, 1);
+            assert_eq!(proposal.vote_end, 11);
+            assert_eq!(governor.state(0), Ok(ProposalState::Pending));
+        }
+
+        #[ink::test]
+        fn cast_vote_fails_while_pending() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut governor = Governor::new(accounts.django, 1, 10, 100);
+            assert_eq!(governor.propose(accounts.django, [0; 4], vec![]), Ok(0));
+
+            assert_eq!(
+                governor.cast_vote(0, VoteType::For),
+                Err(GovernorError::InvalidProposalState)
+            );
+            assert_eq!(
+                governor.cast_vote(1, VoteType::For),
+                Err(GovernorError::ProposalNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn proposal_without_votes_is_defeated() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut governor = Governor::new(accounts.django, 0, 1, 100);
+            assert_eq!(governor.propose(accounts.django, [0; 4], vec![]), Ok(0));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(governor.state(0), Ok(ProposalState::Active));
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(governor.state(0), Ok(ProposalState::Defeated));
+            assert_eq!(governor.execute(0), Err(GovernorError::InvalidProposalState));
+        }
+    }
+}
@@ -0,0 +1,498 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::interest_token::InterestTokenRef;
+
+#[ink::contract]
+mod interest_token {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use ink::env::call::FromAddr;
+    use psp_coin::{PSP22Error, PspCoinRef};
+
+    /// Fixed-point scale of the exchange rate (1e18 = one underlying per share)
+    const WAD: u128 = 1_000_000_000_000_000_000;
+    /// Denominator for the APR in basis points
+    const BPS_DENOMINATOR: u128 = 10_000;
+    /// Milliseconds in a 365-day year
+    const YEAR: Timestamp = 365 * 24 * 60 * 60 * 1000;
+
+    /// Storage structure for the interest-bearing token
+    ///
+    /// Balances are kept as shares of the underlying token. The exchange
+    /// rate of shares to underlying grows at `apr_bps` per year and is
+    /// accrued lazily on every interaction, so `balance_of` grows without
+    /// per-holder writes. Interest is paid out of underlying tokens the
+    /// owner funds the contract with.
+    #[ink(storage)]
+    pub struct InterestToken {
+        /// Account allowed to change the interest rate
+        owner: Address,
+        /// Underlying PSP22 token deposited for shares
+        underlying: Address,
+        /// Simple annual interest rate in basis points, compounded on accrual
+        apr_bps: u16,
+        /// Underlying per share scaled by `WAD`, as of `last_accrual`
+        exchange_rate: u128,
+        /// Time the exchange rate was last accrued
+        last_accrual: Timestamp,
+        /// Total shares in existence
+        total_shares: u128,
+        /// Mapping from account to share balance
+        shares: Mapping<Address, u128>,
+        /// Nested mapping for allowances in underlying terms (owner, spender) -> amount
+        allowances: Mapping<(Address, Address), u128>,
+    }
+
+    /// Event emitted when tokens are transferred, in underlying terms
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        pub from: Option<Address>,
+        #[ink(topic)]
+        pub to: Option<Address>,
+        pub value: u128,
+    }
+
+    /// Event emitted when an approval is granted
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        pub owner: Address,
+        #[ink(topic)]
+        pub spender: Address,
+        pub value: u128,
+    }
+
+    /// Event emitted when interest is accrued
+    #[ink(event)]
+    pub struct Accrued {
+        pub exchange_rate: u128,
+        pub elapsed: Timestamp,
+    }
+
+    /// Event emitted when underlying tokens are deposited for shares
+    #[ink(event)]
+    pub struct Minted {
+        #[ink(topic)]
+        pub account: Address,
+        pub underlying: u128,
+        pub shares: u128,
+    }
+
+    /// Event emitted when shares are redeemed for underlying tokens
+    #[ink(event)]
+    pub struct Redeemed {
+        #[ink(topic)]
+        pub account: Address,
+        pub underlying: u128,
+        pub shares: u128,
+    }
+
+    impl InterestToken {
+        /// Constructor that creates an interest-bearing wrapper of `underlying`
+        #[ink(constructor)]
+        pub fn new(underlying: Address, apr_bps: u16) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                underlying,
+                apr_bps,
+                exchange_rate: WAD,
+                last_accrual: Self::env().block_timestamp(),
+                total_shares: 0,
+                shares: Mapping::default(),
+                allowances: Mapping::default(),
+            }
+        }
+    }
+
+    impl InterestToken {
+        /// Returns the total supply in underlying terms at the stored exchange rate
+        #[ink(message, selector = 0x162DF8C2)]
+        pub fn total_supply(&self) -> u128 {
+            Self::to_underlying(self.total_shares, self.exchange_rate, false)
+        }
+
+        /// Returns the balance of `owner` in underlying terms at the stored exchange rate
+        #[ink(message, selector = 0x6568382F)]
+        pub fn balance_of(&self, owner: Address) -> u128 {
+            Self::to_underlying(self.share_balance_of(owner), self.exchange_rate, false)
+        }
+
+        /// Returns the balance of `owner` in underlying terms including
+        /// interest not yet accrued
+        #[ink(message)]
+        pub fn underlying_balance_of(&self, owner: Address) -> u128 {
+            Self::to_underlying(self.share_balance_of(owner), self.current_exchange_rate(), false)
+        }
+
+        /// Returns the share balance of `owner`
+        #[ink(message)]
+        pub fn share_balance_of(&self, owner: Address) -> u128 {
+            self.shares.get(owner).unwrap_or(0)
+        }
+
+        /// Returns the total shares in existence
+        #[ink(message)]
+        pub fn total_shares(&self) -> u128 {
+            self.total_shares
+        }
+
+        /// Returns the allowance granted by owner to spender
+        #[ink(message, selector = 0x4D47D921)]
+        pub fn allowance(&self, owner: Address, spender: Address) -> u128 {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        /// Returns the underlying token
+        #[ink(message)]
+        pub fn underlying(&self) -> Address {
+            self.underlying
+        }
+
+        /// Returns the annual interest rate in basis points
+        #[ink(message)]
+        pub fn apr_bps(&self) -> u16 {
+            self.apr_bps
+        }
+
+        /// Returns the exchange rate as of the last accrual, scaled by 1e18
+        #[ink(message)]
+        pub fn exchange_rate(&self) -> u128 {
+            self.exchange_rate
+        }
+
+        /// Returns the exchange rate including interest not yet accrued
+        #[ink(message)]
+        pub fn current_exchange_rate(&self) -> u128 {
+            let elapsed = self.env().block_timestamp().saturating_sub(self.last_accrual);
+            let interest = mul_div(
+                self.exchange_rate,
+                u128::from(self.apr_bps) * u128::from(elapsed),
+                BPS_DENOMINATOR * u128::from(YEAR),
+                false,
+            );
+            self.exchange_rate.saturating_add(interest)
+        }
+
+        /// Accrue interest up to the current block and return the new exchange rate
+        #[ink(message)]
+        pub fn accrue(&mut self) -> u128 {
+            let now = self.env().block_timestamp();
+            let elapsed = now.saturating_sub(self.last_accrual);
+            if elapsed == 0 {
+                return self.exchange_rate;
+            }
+
+            self.exchange_rate = self.current_exchange_rate();
+            self.last_accrual = now;
+
+            self.env().emit_event(Accrued {
+                exchange_rate: self.exchange_rate,
+                elapsed,
+            });
+
+            self.exchange_rate
+        }
+
+        /// Change the annual interest rate after accruing at the old one (owner only)
+        #[ink(message)]
+        pub fn set_apr(&mut self, apr_bps: u16) -> Result<(), PSP22Error> {
+            if self.env().caller() != self.owner {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            self.accrue();
+            self.apr_bps = apr_bps;
+            Ok(())
+        }
+
+        /// Transfer `value` in underlying terms from caller to recipient
+        #[ink(message, selector = 0xDB20F9F5)]
+        pub fn transfer(&mut self, to: Address, value: u128, _data: Vec<u8>) -> Result<(), PSP22Error> {
+            let from = self.env().caller();
+            self.transfer_underlying(from, to, value)
+        }
+
+        /// Transfer `value` in underlying terms from one account to another using allowance
+        #[ink(message, selector = 0x54B3C76E)]
+        pub fn transfer_from(
+            &mut self,
+            from: Address,
+            to: Address,
+            value: u128,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            let allowance = self.allowance(from, caller);
+            if allowance < value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+
+            self.transfer_underlying(from, to, value)?;
+            self.allowances.insert((from, caller), &(allowance - value));
+            Ok(())
+        }
+
+        /// Approve spender to spend `value` in underlying terms on behalf of caller
+        #[ink(message, selector = 0xB20F1BBD)]
+        pub fn approve(&mut self, spender: Address, value: u128) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), &value);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Deposit `amount` underlying tokens from the caller for shares
+        ///
+        /// The caller must have approved this contract on the underlying token.
+        #[ink(message)]
+        pub fn mint(&mut self, amount: u128) -> Result<u128, PSP22Error> {
+            let account = self.env().caller();
+            let this = self.env().address();
+            let rate = self.accrue();
+
+            let shares = Self::to_shares(amount, rate, false);
+            if shares == 0 {
+                return Err(PSP22Error::Custom(String::from("Deposit too small")));
+            }
+
+            let mut underlying: PspCoinRef = FromAddr::from_addr(self.underlying);
+            underlying.transfer_from(account, this, amount, Vec::new())?;
+
+            self.total_shares = self
+                .total_shares
+                .checked_add(shares)
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+            self.shares.insert(account, &(self.share_balance_of(account) + shares));
+
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(account),
+                value: amount,
+            });
+            self.env().emit_event(Minted {
+                account,
+                underlying: amount,
+                shares,
+            });
+
+            Ok(shares)
+        }
+
+        /// Burn the caller's shares worth `amount` underlying tokens and pay them out
+        #[ink(message)]
+        pub fn redeem(&mut self, amount: u128) -> Result<u128, PSP22Error> {
+            let account = self.env().caller();
+            let rate = self.accrue();
+
+            let shares = Self::to_shares(amount, rate, true);
+            let new_balance = self
+                .share_balance_of(account)
+                .checked_sub(shares)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.shares.insert(account, &new_balance);
+            self.total_shares -= shares;
+
+            let mut underlying: PspCoinRef = FromAddr::from_addr(self.underlying);
+            underlying.transfer(account, amount, Vec::new())?;
+
+            self.env().emit_event(Transfer {
+                from: Some(account),
+                to: None,
+                value: amount,
+            });
+            self.env().emit_event(Redeemed {
+                account,
+                underlying: amount,
+                shares,
+            });
+
+            Ok(shares)
+        }
+    }
+
+    impl InterestToken {
+        /// Move shares worth `value` underlying tokens, rounding against the sender
+        fn transfer_underlying(&mut self, from: Address, to: Address, value: u128) -> Result<(), PSP22Error> {
+            let rate = self.accrue();
+            let shares = Self::to_shares(value, rate, true);
+
+            let new_from_balance = self
+                .share_balance_of(from)
+                .checked_sub(shares)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.shares.insert(from, &new_from_balance);
+            self.shares.insert(to, &(self.share_balance_of(to) + shares));
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Underlying tokens worth `shares` at `rate`
+        fn to_underlying(shares: u128, rate: u128, round_up: bool) -> u128 {
+            mul_div(shares, rate, WAD, round_up)
+        }
+
+        /// Shares worth `amount` underlying tokens at `rate`
+        fn to_shares(amount: u128, rate: u128, round_up: bool) -> u128 {
+            mul_div(amount, WAD, rate, round_up)
+        }
+    }
+
+    /// Computes `a * b / denominator` in 256-bit precision, saturating on overflow
+    fn mul_div(a: u128, b: u128, denominator: u128, round_up: bool) -> u128 {
+        let product = ink::U256::from(a) * ink::U256::from(b);
+        let denominator = ink::U256::from(denominator);
+        let mut quotient = product / denominator;
+        if round_up && !(product % denominator).is_zero() {
+            quotient += ink::U256::one();
+        }
+
+        if quotient > ink::U256::from(u128::MAX) {
+            return u128::MAX;
+        }
+        quotient.low_u128()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn set_time(timestamp: Timestamp) {
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(timestamp);
+        }
+
+        #[ink::test]
+        fn interest_accrues_into_balances() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+            set_time(0);
+
+            let mut token = InterestToken::new(accounts.django, 1_000);
+            token.shares.insert(accounts.alice, &1_000);
+            token.total_shares = 1_000;
+            assert_eq!(token.balance_of(accounts.alice), 1_000);
+
+            // A year at 10% APR is visible before accrual only in the projection
+            set_time(YEAR);
+            assert_eq!(token.balance_of(accounts.alice), 1_000);
+            assert_eq!(token.underlying_balance_of(accounts.alice), 1_100);
+
+            assert_eq!(token.accrue(), WAD / 10 * 11);
+            assert_eq!(token.balance_of(accounts.alice), 1_100);
+            assert_eq!(token.total_supply(), 1_100);
+            assert_eq!(token.share_balance_of(accounts.alice), 1_000);
+        }
+
+        #[ink::test]
+        fn transfer_moves_shares_at_current_rate() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+            set_time(0);
+
+            let mut token = InterestToken::new(accounts.django, 1_000);
+            token.shares.insert(accounts.alice, &1_000);
+            token.total_shares = 1_000;
+
+            set_time(YEAR);
+            assert_eq!(token.transfer(accounts.bob, 550, Vec::new()), Ok(()));
+            assert_eq!(token.share_balance_of(accounts.bob), 500);
+            assert_eq!(token.balance_of(accounts.bob), 550);
+            assert_eq!(token.balance_of(accounts.alice), 550);
+            assert_eq!(
+                token.transfer(accounts.bob, 551, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn set_apr_accrues_at_old_rate() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+            set_time(0);
+
+            let mut token = InterestToken::new(accounts.django, 1_000);
+            set_time(YEAR);
+            assert_eq!(token.set_apr(0), Ok(()));
+            assert_eq!(token.exchange_rate(), WAD / 10 * 11);
+
+            set_time(2 * YEAR);
+            assert_eq!(token.current_exchange_rate(), WAD / 10 * 11);
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(token.set_apr(500), Err(PSP22Error::Unauthorized));
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::PspCoin;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn mint_and_redeem_round_trip<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given an underlying token and an interest-free wrapper of it
+            let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+
+            let mut underlying_constructor = PspCoinRef::new_with_supply(1_000_000);
+            let underlying = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut underlying_constructor)
+                .submit()
+                .await
+                .expect("underlying instantiate failed");
+            let mut underlying_call = underlying.call_builder::<PspCoin>();
+
+            let mut token_constructor = InterestTokenRef::new(underlying.addr, 0);
+            let token = client
+                .instantiate("interest_token", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("interest token instantiate failed");
+            let mut token_call = token.call_builder::<InterestToken>();
+
+            // When alice deposits underlying tokens
+            client
+                .call(&ink_e2e::alice(), &underlying_call.approve(token.addr, 1_000))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::alice(), &token_call.mint(1_000))
+                .submit()
+                .await?;
+
+            // Then she holds the deposit and can redeem it in full
+            let balance = client
+                .call(&ink_e2e::alice(), &token_call.balance_of(alice))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, 1_000);
+
+            client
+                .call(&ink_e2e::alice(), &token_call.redeem(1_000))
+                .submit()
+                .await?;
+            let underlying_balance = client
+                .call(&ink_e2e::alice(), &underlying_call.balance_of(alice))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(underlying_balance, 1_000_000);
+
+            Ok(())
+        }
+    }
+}
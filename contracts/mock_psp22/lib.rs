@@ -0,0 +1,390 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::mock_psp22::{MockPsp22, MockPsp22Ref, Reentry};
+
+#[ink::contract]
+mod mock_psp22 {
+    use ink::{storage::{Lazy, Mapping}, prelude::vec::Vec};
+    use ink::env::{
+        call::{build_call, ExecutionInput, Selector},
+        DefaultEnvironment,
+    };
+    use ink::scale::{Decode, Encode, Input, Output};
+    use psp22_interface::{PSP22, PSP22Error};
+
+    /// Raw, already SCALE encoded call arguments
+    struct CallInput<'a>(&'a [u8]);
+
+    impl Encode for CallInput<'_> {
+        fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(self.0);
+        }
+    }
+
+    /// Return data of a re-entrant call, consumed without being decoded
+    struct CallOutput;
+
+    impl Decode for CallOutput {
+        fn decode<I: Input>(input: &mut I) -> Result<Self, ink::scale::Error> {
+            while input.read_byte().is_ok() {}
+            Ok(CallOutput)
+        }
+    }
+
+    /// Call the mock makes before moving any tokens
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Reentry {
+        /// Contract to call, usually the one calling the mock
+        pub callee: Address,
+        /// Selector of the message to call
+        pub selector: [u8; 4],
+        /// SCALE encoded arguments of the message
+        pub input: Vec<u8>,
+    }
+
+    /// Storage structure for the mock PSP22 token
+    ///
+    /// A PSP22 test double for contracts that call a token. It keeps real
+    /// balances and allowances, but can be told to fail every mutating
+    /// call, report a wrong balance, report success without moving tokens,
+    /// or call back into another contract mid-transfer. Configuration
+    /// messages are open to anyone: never deploy it outside tests.
+    #[ink(storage)]
+    pub struct MockPsp22 {
+        /// Token balance of each account
+        balances: Mapping<Address, u128>,
+        /// Allowance of each (owner, spender) pair
+        allowances: Mapping<(Address, Address), u128>,
+        /// Sum of all balances
+        total_supply: u128,
+        /// Error code every transfer and approval fails with, if set
+        fail_with: Option<u32>,
+        /// Balance `balance_of` reports for every account, if set
+        reported_balance: Option<u128>,
+        /// Whether transfers report success without moving tokens
+        silent_transfers: bool,
+        /// Call made at the start of the next transfer, if set
+        ///
+        /// Kept in `Lazy` so it is cleared in storage before the call goes
+        /// out, and a transfer made by the callee does not re-enter again.
+        reentry: Lazy<Option<Reentry>>,
+        /// Number of re-entrant calls made
+        reentries: u32,
+        /// Whether the last re-entrant call failed
+        last_reentry_failed: bool,
+    }
+
+    impl MockPsp22 {
+        /// Constructor that mints `supply` to the caller and behaves like a
+        /// regular token until configured otherwise
+        #[ink(constructor)]
+        pub fn new(supply: u128) -> Self {
+            let mut balances = Mapping::default();
+            balances.insert(Self::env().caller(), &supply);
+            Self {
+                balances,
+                allowances: Mapping::default(),
+                total_supply: supply,
+                fail_with: None,
+                reported_balance: None,
+                silent_transfers: false,
+                reentry: Lazy::default(),
+                reentries: 0,
+                last_reentry_failed: false,
+            }
+        }
+    }
+
+    impl PSP22 for MockPsp22 {
+        #[ink(message)]
+        fn total_supply(&self) -> u128 {
+            self.total_supply
+        }
+
+        #[ink(message)]
+        fn balance_of(&self, owner: Address) -> u128 {
+            self.reported_balance
+                .unwrap_or_else(|| self.balances.get(owner).unwrap_or(0))
+        }
+
+        #[ink(message)]
+        fn allowance(&self, owner: Address, spender: Address) -> u128 {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        fn transfer(&mut self, to: Address, value: u128, _data: Vec<u8>) -> Result<(), PSP22Error> {
+            self.misbehave()?;
+            let from = self.env().caller();
+            self.move_tokens(from, to, value)
+        }
+
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: Address,
+            to: Address,
+            value: u128,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            self.misbehave()?;
+            let spender = self.env().caller();
+            let allowance = self.allowance(from, spender);
+            if allowance < value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+            self.move_tokens(from, to, value)?;
+            self.allowances.insert((from, spender), &(allowance - value));
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn approve(&mut self, spender: Address, value: u128) -> Result<(), PSP22Error> {
+            self.fail()?;
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), &value);
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn increase_allowance(&mut self, spender: Address, delta_value: u128) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            let value = self.allowance(owner, spender).saturating_add(delta_value);
+            self.approve(spender, value)
+        }
+
+        #[ink(message)]
+        fn decrease_allowance(&mut self, spender: Address, delta_value: u128) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            let value = self
+                .allowance(owner, spender)
+                .checked_sub(delta_value)
+                .ok_or(PSP22Error::InsufficientAllowance)?;
+            self.approve(spender, value)
+        }
+    }
+
+    impl MockPsp22 {
+        /// Make every transfer and approval fail with the error of code
+        /// `code`, or behave normally again with `None`
+        #[ink(message)]
+        pub fn set_fail_with(&mut self, code: Option<u32>) {
+            self.fail_with = code;
+        }
+
+        /// Make `balance_of` report `balance` for every account, or the real
+        /// balances again with `None`
+        #[ink(message)]
+        pub fn set_reported_balance(&mut self, balance: Option<u128>) {
+            self.reported_balance = balance;
+        }
+
+        /// Make transfers report success without moving any tokens
+        #[ink(message)]
+        pub fn set_silent_transfers(&mut self, silent: bool) {
+            self.silent_transfers = silent;
+        }
+
+        /// Make the next transfer start with `reentry`, or cancel it with `None`
+        #[ink(message)]
+        pub fn set_reentry(&mut self, reentry: Option<Reentry>) {
+            self.reentry.set(&reentry);
+        }
+
+        /// Returns the number of re-entrant calls made
+        #[ink(message)]
+        pub fn reentries(&self) -> u32 {
+            self.reentries
+        }
+
+        /// Returns whether the last re-entrant call failed
+        #[ink(message)]
+        pub fn last_reentry_failed(&self) -> bool {
+            self.last_reentry_failed
+        }
+
+        /// Create `value` tokens for `to`
+        #[ink(message)]
+        pub fn mint(&mut self, to: Address, value: u128) {
+            let balance = self.balances.get(to).unwrap_or(0);
+            self.balances.insert(to, &balance.saturating_add(value));
+            self.total_supply = self.total_supply.saturating_add(value);
+        }
+    }
+
+    impl MockPsp22 {
+        /// Fails with the configured error, if any
+        fn fail(&self) -> Result<(), PSP22Error> {
+            match self.fail_with {
+                Some(code) => Err(PSP22Error::from(code)),
+                None => Ok(()),
+            }
+        }
+
+        /// Fails with the configured error, then makes the configured
+        /// re-entrant call, recording whether it failed
+        fn misbehave(&mut self) -> Result<(), PSP22Error> {
+            self.fail()?;
+
+            if let Some(reentry) = self.reentry.get().flatten() {
+                self.reentry.set(&None);
+
+                let result = build_call::<DefaultEnvironment>()
+                    .call(reentry.callee)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(reentry.selector))
+                            .push_arg(CallInput(&reentry.input)),
+                    )
+                    .returns::<CallOutput>()
+                    .try_invoke();
+
+                self.reentries = self.reentries.saturating_add(1);
+                self.last_reentry_failed = !matches!(result, Ok(Ok(_)));
+            }
+
+            Ok(())
+        }
+
+        /// Moves `value` from `from` to `to`, unless transfers are silent
+        fn move_tokens(&mut self, from: Address, to: Address, value: u128) -> Result<(), PSP22Error> {
+            if self.silent_transfers || from == to {
+                return Ok(());
+            }
+
+            let from_balance = self.balances.get(from).unwrap_or(0);
+            if from_balance < value {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+            let to_balance = self.balances.get(to).unwrap_or(0);
+
+            self.balances.insert(from, &(from_balance - value));
+            self.balances.insert(to, &to_balance.saturating_add(value));
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn configured_failures_replace_normal_behavior() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+            let mut token = MockPsp22::new(1_000);
+
+            // Behaves like a token until configured
+            assert_eq!(token.transfer(accounts.bob, 100, Vec::new()), Ok(()));
+            assert_eq!(token.balance_of(accounts.bob), 100);
+
+            // Fails with the configured error
+            token.set_fail_with(Some(PSP22Error::InsufficientBalance.code()));
+            assert_eq!(
+                token.transfer(accounts.bob, 100, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+            assert_eq!(token.approve(accounts.bob, 100), Err(PSP22Error::InsufficientBalance));
+            token.set_fail_with(None);
+
+            // Reports success without moving anything
+            token.set_silent_transfers(true);
+            assert_eq!(token.transfer(accounts.bob, 100, Vec::new()), Ok(()));
+            assert_eq!(token.balance_of(accounts.bob), 100);
+
+            // Reports a wrong balance
+            token.set_reported_balance(Some(u128::MAX));
+            assert_eq!(token.balance_of(accounts.charlie), u128::MAX);
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use paid_counter::{PaidCounter, PaidCounterRef};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn counter_surfaces_token_failures<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a paid counter whose token is the mock
+            let mut token_constructor = MockPsp22Ref::new(1_000);
+            let token = client
+                .instantiate("mock_psp22", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("mock instantiate failed");
+            let mut token_call = token.call_builder::<MockPsp22>();
+
+            let mut counter_constructor = PaidCounterRef::new(token.addr, 10);
+            let counter = client
+                .instantiate("paid_counter", &ink_e2e::alice(), &mut counter_constructor)
+                .submit()
+                .await
+                .expect("counter instantiate failed");
+            let mut counter_call = counter.call_builder::<PaidCounter>();
+
+            client
+                .call(&ink_e2e::alice(), &token_call.approve(counter.addr, 100))
+                .submit()
+                .await?;
+
+            // When the token fails every transfer, the increment fails with it
+            client
+                .call(&ink_e2e::alice(), &token_call.set_fail_with(Some(PSP22Error::Frozen.code())))
+                .submit()
+                .await?;
+            let refused = client
+                .call(&ink_e2e::alice(), &counter_call.increment())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(refused, Err(PSP22Error::Frozen));
+
+            // And when the token re-enters the counter mid-payment, the
+            // nested increment cannot pay, since the mock approved nothing
+            client
+                .call(&ink_e2e::alice(), &token_call.set_fail_with(None))
+                .submit()
+                .await?;
+            let reentry = Reentry {
+                callee: counter.addr,
+                selector: ink::selector_bytes!("increment"),
+                input: Vec::new(),
+            };
+            client
+                .call(&ink_e2e::alice(), &token_call.set_reentry(Some(reentry)))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::alice(), &counter_call.increment())
+                .submit()
+                .await?;
+
+            let reentries = client
+                .call(&ink_e2e::alice(), &token_call.reentries())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(reentries, 1);
+            let failed = client
+                .call(&ink_e2e::alice(), &token_call.last_reentry_failed())
+                .dry_run()
+                .await?
+                .return_value();
+            assert!(failed);
+            let total = client
+                .call(&ink_e2e::alice(), &counter_call.total())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(total, 1);
+
+            Ok(())
+        }
+    }
+}
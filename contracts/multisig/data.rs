@@ -0,0 +1,43 @@
+use ink::prelude::vec::Vec;
+use ink::Address;
+
+/// Multisig error types
+#[derive(Debug, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum MultisigError {
+    /// Caller is not one of the multisig owners
+    NotOwner,
+    /// Threshold is zero or larger than the number of owners
+    InvalidThreshold,
+    /// No transaction exists with the given id
+    TransactionNotFound,
+    /// Transaction was already executed
+    AlreadyExecuted,
+    /// Caller already confirmed the transaction
+    AlreadyConfirmed,
+    /// Caller has not confirmed the transaction
+    NotConfirmed,
+    /// Transaction has fewer confirmations than the threshold
+    NotEnoughConfirmations,
+    /// The transaction call failed
+    ExecutionFailed,
+    /// Arithmetic overflow
+    Overflow,
+}
+
+/// A call submitted for approval by the owners
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Transaction {
+    /// Contract to call
+    pub callee: Address,
+    /// Selector of the message to call
+    pub selector: [u8; 4],
+    /// SCALE encoded message arguments
+    pub input: Vec<u8>,
+    /// Number of owners that confirmed the transaction
+    pub confirmations: u32,
+    /// Whether the transaction has been executed
+    pub executed: bool,
+}
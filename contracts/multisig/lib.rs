@@ -0,0 +1,406 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+mod data;
+
+pub use data::{MultisigError, Transaction};
+pub use self::multisig::MultisigRef;
+
+#[ink::contract]
+mod multisig {
+    use ink::{storage::Mapping, prelude::vec::Vec};
+    use ink::env::{
+        call::{build_call, ExecutionInput, Selector},
+        DefaultEnvironment,
+    };
+    use ink::scale::{Decode, Encode, Input, Output};
+
+    use crate::data::{MultisigError, Transaction};
+
+    /// Raw, already SCALE encoded call arguments
+    struct CallInput<'a>(&'a [u8]);
+
+    impl Encode for CallInput<'_> {
+        fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(self.0);
+        }
+    }
+
+    /// Return data of an executed call, consumed without being decoded
+    struct CallOutput;
+
+    impl Decode for CallOutput {
+        fn decode<I: Input>(input: &mut I) -> Result<Self, ink::scale::Error> {
+            while input.read_byte().is_ok() {}
+            Ok(CallOutput)
+        }
+    }
+
+    /// Storage structure for the multisig wallet
+    #[ink(storage)]
+    pub struct Multisig {
+        /// Accounts allowed to submit and confirm transactions
+        owners: Vec<Address>,
+        /// Confirmations required to execute a transaction
+        threshold: u32,
+        /// Id assigned to the next submitted transaction
+        next_transaction_id: u32,
+        /// Submitted transactions by id
+        transactions: Mapping<u32, Transaction>,
+        /// Confirmations by (transaction id, owner)
+        confirmations: Mapping<(u32, Address), ()>,
+    }
+
+    /// Event emitted when a transaction is submitted
+    #[ink(event)]
+    pub struct Submission {
+        #[ink(topic)]
+        pub transaction_id: u32,
+        #[ink(topic)]
+        pub owner: Address,
+    }
+
+    /// Event emitted when an owner confirms a transaction
+    #[ink(event)]
+    pub struct Confirmation {
+        #[ink(topic)]
+        pub transaction_id: u32,
+        #[ink(topic)]
+        pub owner: Address,
+    }
+
+    /// Event emitted when an owner revokes its confirmation
+    #[ink(event)]
+    pub struct Revocation {
+        #[ink(topic)]
+        pub transaction_id: u32,
+        #[ink(topic)]
+        pub owner: Address,
+    }
+
+    /// Event emitted when a transaction is executed
+    #[ink(event)]
+    pub struct Execution {
+        #[ink(topic)]
+        pub transaction_id: u32,
+    }
+
+    impl Multisig {
+        /// Constructor that sets the owners and the confirmation threshold
+        #[ink(constructor)]
+        pub fn new(owners: Vec<Address>, threshold: u32) -> Result<Self, MultisigError> {
+            if threshold == 0 || threshold as usize > owners.len() {
+                return Err(MultisigError::InvalidThreshold);
+            }
+
+            Ok(Self {
+                owners,
+                threshold,
+                next_transaction_id: 0,
+                transactions: Mapping::default(),
+                confirmations: Mapping::default(),
+            })
+        }
+    }
+
+    impl Multisig {
+        /// Returns the multisig owners
+        #[ink(message)]
+        pub fn owners(&self) -> Vec<Address> {
+            self.owners.clone()
+        }
+
+        /// Returns the number of confirmations required for execution
+        #[ink(message)]
+        pub fn threshold(&self) -> u32 {
+            self.threshold
+        }
+
+        /// Returns the transaction with the given id
+        #[ink(message)]
+        pub fn transaction(&self, transaction_id: u32) -> Option<Transaction> {
+            self.transactions.get(transaction_id)
+        }
+
+        /// Returns whether `owner` confirmed the transaction
+        #[ink(message)]
+        pub fn is_confirmed_by(&self, transaction_id: u32, owner: Address) -> bool {
+            self.confirmations.contains((transaction_id, owner))
+        }
+
+        /// Submit a call for approval and confirm it on behalf of the caller
+        #[ink(message)]
+        pub fn submit_transaction(
+            &mut self,
+            callee: Address,
+            selector: [u8; 4],
+            input: Vec<u8>,
+        ) -> Result<u32, MultisigError> {
+            let owner = self.ensure_owner()?;
+
+            let transaction_id = self.next_transaction_id;
+            self.next_transaction_id = transaction_id
+                .checked_add(1)
+                .ok_or(MultisigError::Overflow)?;
+
+            self.transactions.insert(
+                transaction_id,
+                &Transaction {
+                    callee,
+                    selector,
+                    input,
+                    confirmations: 0,
+                    executed: false,
+                },
+            );
+
+            self.env().emit_event(Submission {
+                transaction_id,
+                owner,
+            });
+
+            self.confirm(transaction_id)?;
+
+            Ok(transaction_id)
+        }
+
+        /// Confirm a pending transaction
+        #[ink(message)]
+        pub fn confirm(&mut self, transaction_id: u32) -> Result<(), MultisigError> {
+            let owner = self.ensure_owner()?;
+            let mut transaction = self.pending_transaction(transaction_id)?;
+
+            if self.confirmations.contains((transaction_id, owner)) {
+                return Err(MultisigError::AlreadyConfirmed);
+            }
+
+            transaction.confirmations += 1;
+            self.transactions.insert(transaction_id, &transaction);
+            self.confirmations.insert((transaction_id, owner), &());
+
+            self.env().emit_event(Confirmation {
+                transaction_id,
+                owner,
+            });
+
+            Ok(())
+        }
+
+        /// Withdraw the caller's confirmation of a pending transaction
+        #[ink(message)]
+        pub fn revoke(&mut self, transaction_id: u32) -> Result<(), MultisigError> {
+            let owner = self.ensure_owner()?;
+            let mut transaction = self.pending_transaction(transaction_id)?;
+
+            if self.confirmations.take((transaction_id, owner)).is_none() {
+                return Err(MultisigError::NotConfirmed);
+            }
+
+            transaction.confirmations -= 1;
+            self.transactions.insert(transaction_id, &transaction);
+
+            self.env().emit_event(Revocation {
+                transaction_id,
+                owner,
+            });
+
+            Ok(())
+        }
+
+        /// Execute a transaction that reached the confirmation threshold
+        #[ink(message)]
+        pub fn execute(&mut self, transaction_id: u32) -> Result<(), MultisigError> {
+            self.ensure_owner()?;
+            let mut transaction = self.pending_transaction(transaction_id)?;
+
+            if transaction.confirmations < self.threshold {
+                return Err(MultisigError::NotEnoughConfirmations);
+            }
+
+            // Mark executed before the call so it cannot be executed twice
+            transaction.executed = true;
+            self.transactions.insert(transaction_id, &transaction);
+
+            build_call::<DefaultEnvironment>()
+                .call(transaction.callee)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(transaction.selector))
+                        .push_arg(CallInput(&transaction.input)),
+                )
+                .returns::<CallOutput>()
+                .try_invoke()
+                .map_err(|_| MultisigError::ExecutionFailed)?
+                .map_err(|_| MultisigError::ExecutionFailed)?;
+
+            self.env().emit_event(Execution { transaction_id });
+
+            Ok(())
+        }
+    }
+
+    impl Multisig {
+        /// Returns the caller if it is one of the owners
+        fn ensure_owner(&self) -> Result<Address, MultisigError> {
+            let caller = self.env().caller();
+            if !self.owners.contains(&caller) {
+                return Err(MultisigError::NotOwner);
+            }
+            Ok(caller)
+        }
+
+        /// Returns the transaction if it exists and was not executed yet
+        fn pending_transaction(&self, transaction_id: u32) -> Result<Transaction, MultisigError> {
+            let transaction = self
+                .transactions
+                .get(transaction_id)
+                .ok_or(MultisigError::TransactionNotFound)?;
+
+            if transaction.executed {
+                return Err(MultisigError::AlreadyExecuted);
+            }
+            Ok(transaction)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn new_fails_with_invalid_threshold() {
+            let accounts = ink::env::test::default_accounts();
+
+            assert_eq!(
+                Multisig::new(vec![accounts.alice], 2).err(),
+                Some(MultisigError::InvalidThreshold)
+            );
+            assert_eq!(
+                Multisig::new(vec![accounts.alice], 0).err(),
+                Some(MultisigError::InvalidThreshold)
+            );
+        }
+
+        #[ink::test]
+        fn submit_confirm_and_revoke_work() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut multisig =
+                Multisig::new(vec![accounts.alice, accounts.bob], 2).expect("valid threshold");
+
+            assert_eq!(multisig.submit_transaction(accounts.django, [0; 4], vec![]), Ok(0));
+            assert!(multisig.is_confirmed_by(0, accounts.alice));
+            assert_eq!(multisig.execute(0), Err(MultisigError::NotEnoughConfirmations));
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(multisig.confirm(0), Ok(()));
+            assert_eq!(multisig.confirm(0), Err(MultisigError::AlreadyConfirmed));
+            assert_eq!(multisig.revoke(0), Ok(()));
+            assert_eq!(multisig.transaction(0).map(|tx| tx.confirmations), Some(1));
+        }
+
+        #[ink::test]
+        fn non_owner_cannot_submit() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.charlie);
+
+            let mut multisig =
+                Multisig::new(vec![accounts.alice, accounts.bob], 2).expect("valid threshold");
+
+            assert_eq!(
+                multisig.submit_transaction(accounts.django, [0; 4], vec![]),
+                Err(MultisigError::NotOwner)
+            );
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::{PspCoin, PspCoinRef};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        /// Selector of the token's owner-gated `mint_to(to, value)`
+        const MINT_TO_SELECTOR: [u8; 4] = [0x1d, 0x2f, 0x13, 0xc5];
+
+        #[ink_e2e::test]
+        async fn multisig_owned_token_mints_after_threshold<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a token and a 2-of-2 multisig owning it
+            let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+            let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+            let charlie = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::charlie());
+
+            let mut token_constructor = PspCoinRef::new();
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut multisig_constructor = MultisigRef::new(vec![alice, bob], 2);
+            let multisig = client
+                .instantiate("multisig", &ink_e2e::alice(), &mut multisig_constructor)
+                .submit()
+                .await
+                .expect("multisig instantiate failed");
+            let mut multisig_call = multisig.call_builder::<Multisig>();
+
+            client
+                .call(&ink_e2e::alice(), &token_call.transfer_ownership(multisig.addr))
+                .submit()
+                .await
+                .expect("transfer_ownership failed");
+
+            // When alice submits a mint and bob confirms and executes it
+            let input = (charlie, 100u128).encode();
+            client
+                .call(
+                    &ink_e2e::alice(),
+                    &multisig_call.submit_transaction(token.addr, MINT_TO_SELECTOR, input),
+                )
+                .submit()
+                .await
+                .expect("submit failed");
+
+            let early = client
+                .call(&ink_e2e::alice(), &multisig_call.execute(0))
+                .dry_run()
+                .await?;
+            assert_eq!(
+                early.return_value(),
+                Err(MultisigError::NotEnoughConfirmations)
+            );
+
+            client
+                .call(&ink_e2e::bob(), &multisig_call.confirm(0))
+                .submit()
+                .await
+                .expect("confirm failed");
+            client
+                .call(&ink_e2e::bob(), &multisig_call.execute(0))
+                .submit()
+                .await
+                .expect("execute failed");
+
+            // Then the tokens were minted
+            let balance = client
+                .call(&ink_e2e::alice(), &token_call.balance_of(charlie))
+                .dry_run()
+                .await?;
+            assert_eq!(balance.return_value(), 100);
+
+            // And the owner-gated message rejects direct calls
+            let direct = client
+                .call(&ink_e2e::alice(), &token_call.mint_to(alice, 1))
+                .dry_run()
+                .await?;
+            assert_eq!(direct.return_value(), Err(psp_coin::PSP22Error::Unauthorized));
+
+            Ok(())
+        }
+    }
+}
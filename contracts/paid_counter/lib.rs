@@ -0,0 +1,221 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::paid_counter::PaidCounterRef;
+
+#[ink::contract]
+mod paid_counter {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use psp22_interface::{PSP22Error, PSP22Ref};
+
+    /// Storage structure for the paid counter
+    ///
+    /// Every increment costs `price` tokens, pulled from the caller with
+    /// `transfer_from`. The token is only known through the `PSP22`
+    /// interface, so any PSP22 token works and its implementation is never
+    /// compiled into this contract.
+    #[ink(storage)]
+    pub struct PaidCounter {
+        /// PSP22 token increments are paid in
+        token: Address,
+        /// Tokens charged per increment
+        price: u128,
+        /// Account allowed to withdraw the collected tokens
+        owner: Address,
+        /// Increments made by all accounts
+        total: u64,
+        /// Increments made by each account
+        counts: Mapping<Address, u64>,
+    }
+
+    /// Event emitted when an account pays to increment the counter
+    #[ink(event)]
+    pub struct Incremented {
+        #[ink(topic)]
+        pub by: Address,
+        pub total: u64,
+        pub paid: u128,
+    }
+
+    impl PaidCounter {
+        /// Constructor charging `price` of `token` per increment, with the caller as owner
+        #[ink(constructor)]
+        pub fn new(token: Address, price: u128) -> Self {
+            Self {
+                token,
+                price,
+                owner: Self::env().caller(),
+                total: 0,
+                counts: Mapping::default(),
+            }
+        }
+    }
+
+    impl PaidCounter {
+        /// Returns the token increments are paid in
+        #[ink(message)]
+        pub fn token(&self) -> Address {
+            self.token
+        }
+
+        /// Returns the tokens charged per increment
+        #[ink(message)]
+        pub fn price(&self) -> u128 {
+            self.price
+        }
+
+        /// Returns the increments made by all accounts
+        #[ink(message)]
+        pub fn total(&self) -> u64 {
+            self.total
+        }
+
+        /// Returns the increments made by `account`
+        #[ink(message)]
+        pub fn count_of(&self, account: Address) -> u64 {
+            self.counts.get(account).unwrap_or(0)
+        }
+
+        /// Pay `price` tokens to increment the counter
+        ///
+        /// The caller must first approve this contract for at least `price`.
+        #[ink(message)]
+        pub fn increment(&mut self) -> Result<u64, PSP22Error> {
+            let caller = self.env().caller();
+
+            if self.price > 0 {
+                let mut token: PSP22Ref = self.token.into();
+                token.transfer_from(caller, self.env().address(), self.price, Vec::new())?;
+            }
+
+            let overflow = || PSP22Error::Custom(String::from("Overflow"));
+            self.total = self.total.checked_add(1).ok_or_else(overflow)?;
+            let count = self.count_of(caller).checked_add(1).ok_or_else(overflow)?;
+            self.counts.insert(caller, &count);
+
+            self.env().emit_event(Incremented {
+                by: caller,
+                total: self.total,
+                paid: self.price,
+            });
+
+            Ok(self.total)
+        }
+
+        /// Send every collected token to `to` (owner only)
+        #[ink(message)]
+        pub fn withdraw(&mut self, to: Address) -> Result<u128, PSP22Error> {
+            if self.env().caller() != self.owner {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            let mut token: PSP22Ref = self.token.into();
+            let collected = token.balance_of(self.env().address());
+            token.transfer(to, collected, Vec::new())?;
+
+            Ok(collected)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn free_counter_counts_per_account() {
+            let accounts = ink::env::test::default_accounts();
+            let mut counter = PaidCounter::new(accounts.django, 0);
+
+            ink::env::test::set_caller(accounts.alice);
+            assert_eq!(counter.increment(), Ok(1));
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(counter.increment(), Ok(2));
+            assert_eq!(counter.increment(), Ok(3));
+
+            assert_eq!(counter.count_of(accounts.alice), 1);
+            assert_eq!(counter.count_of(accounts.bob), 2);
+            assert_eq!(counter.withdraw(accounts.bob), Err(PSP22Error::Unauthorized));
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::{PspCoin, PspCoinRef};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn increments_are_paid_in_tokens<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a counter charging 25 tokens and bob holding 100
+            let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+            let charlie = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::charlie());
+
+            let mut token_constructor = PspCoinRef::new_with_supply(1_000);
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut counter_constructor = PaidCounterRef::new(token.addr, 25);
+            let counter = client
+                .instantiate("paid_counter", &ink_e2e::alice(), &mut counter_constructor)
+                .submit()
+                .await
+                .expect("counter instantiate failed");
+            let mut counter_call = counter.call_builder::<PaidCounter>();
+
+            client
+                .call(&ink_e2e::alice(), &token_call.transfer(bob, 100, Vec::new()))
+                .submit()
+                .await?;
+
+            // When bob approves the counter and increments twice
+            client
+                .call(&ink_e2e::bob(), &token_call.approve(counter.addr, 50))
+                .submit()
+                .await?;
+            for _ in 0..2 {
+                client
+                    .call(&ink_e2e::bob(), &counter_call.increment())
+                    .submit()
+                    .await?;
+            }
+
+            // Then the counter holds the payments and a third increment is refused
+            let count = client
+                .call(&ink_e2e::bob(), &counter_call.count_of(bob))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(count, 2);
+
+            let unpaid = client
+                .call(&ink_e2e::bob(), &counter_call.increment())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(unpaid, Err(PSP22Error::InsufficientAllowance));
+
+            // And the owner withdraws the collected tokens
+            let withdrawn = client
+                .call(&ink_e2e::alice(), &counter_call.withdraw(charlie))
+                .submit()
+                .await?
+                .return_value();
+            assert_eq!(withdrawn, Ok(50));
+
+            let balance = client
+                .call(&ink_e2e::alice(), &token_call.balance_of(charlie))
+                .dry_run()
+                .await?;
+            assert_eq!(balance.return_value(), 50);
+
+            Ok(())
+        }
+    }
+}
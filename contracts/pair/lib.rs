@@ -0,0 +1,771 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::pair::{Pair, PairRef};
+
+/// Liquidity permanently locked in the pair on the first deposit
+pub const MINIMUM_LIQUIDITY: u128 = 1_000;
+
+/// Minimum time between two TWAP observations (10 minutes)
+pub const TWAP_PERIOD: u64 = 600_000;
+
+/// Output of swapping `amount_in` against the given reserves, after the 0.3% fee
+pub fn get_amount_out(amount_in: u128, reserve_in: u128, reserve_out: u128) -> Option<u128> {
+    if amount_in == 0 || reserve_in == 0 || reserve_out == 0 {
+        return None;
+    }
+
+    let amount_in_with_fee = ink::U256::from(amount_in) * ink::U256::from(997);
+    let numerator = amount_in_with_fee * ink::U256::from(reserve_out);
+    let denominator = ink::U256::from(reserve_in) * ink::U256::from(1_000) + amount_in_with_fee;
+
+    // Always below `reserve_out`, so it fits
+    Some((numerator / denominator).low_u128())
+}
+
+#[ink::contract]
+mod pair {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use ink::env::call::FromAddr;
+    use ink::U256;
+    use psp_coin::{PSP22Error, PspCoinRef};
+
+    use crate::{get_amount_out, MINIMUM_LIQUIDITY, TWAP_PERIOD};
+
+    /// Fixed point scale of the cumulative prices (2^112)
+    const Q112: U256 = U256([0, 1 << 48, 0, 0]);
+
+    /// Storage structure for the constant-product pair
+    ///
+    /// Holds reserves of `token0` and either a second PSP22 token or the
+    /// native currency. Liquidity providers receive LP shares, which are
+    /// themselves a PSP22 token. Time-weighted prices are accumulated on
+    /// every reserve change so the pair can serve as a price oracle.
+    #[ink(storage)]
+    pub struct Pair {
+        /// First asset of the pair
+        token0: Address,
+        /// Second asset of the pair, `None` for the native currency
+        token1: Option<Address>,
+        /// Reserve of `token0`
+        reserve0: u128,
+        /// Reserve of `token1`
+        reserve1: u128,
+        /// Total supply of LP shares
+        total_supply: u128,
+        /// Mapping from account to LP share balance
+        balances: Mapping<Address, u128>,
+        /// Nested mapping for LP share allowances (owner, spender) -> amount
+        allowances: Mapping<(Address, Address), u128>,
+        /// Sum of `token0` prices in `token1` weighted by time, scaled by 2^112
+        price0_cumulative: U256,
+        /// Sum of `token1` prices in `token0` weighted by time, scaled by 2^112
+        price1_cumulative: U256,
+        /// Time of the last reserve change
+        last_update: Timestamp,
+        /// Cumulative prices and time of the latest observation
+        observation: (U256, U256, Timestamp),
+        /// The observation replaced by `observation`, at least `TWAP_PERIOD`
+        /// older than it
+        previous_observation: (U256, U256, Timestamp),
+    }
+
+    /// Event emitted when LP shares are transferred
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        pub from: Option<Address>,
+        #[ink(topic)]
+        pub to: Option<Address>,
+        pub value: u128,
+    }
+
+    /// Event emitted when an LP share approval is granted
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        pub owner: Address,
+        #[ink(topic)]
+        pub spender: Address,
+        pub value: u128,
+    }
+
+    /// Event emitted when liquidity is added
+    #[ink(event)]
+    pub struct Mint {
+        #[ink(topic)]
+        pub sender: Address,
+        pub amount0: u128,
+        pub amount1: u128,
+        pub liquidity: u128,
+    }
+
+    /// Event emitted when liquidity is removed
+    #[ink(event)]
+    pub struct Burn {
+        #[ink(topic)]
+        pub sender: Address,
+        pub amount0: u128,
+        pub amount1: u128,
+        pub liquidity: u128,
+    }
+
+    /// Event emitted on every swap
+    #[ink(event)]
+    pub struct Swap {
+        #[ink(topic)]
+        pub sender: Address,
+        #[ink(topic)]
+        pub to: Address,
+        pub zero_for_one: bool,
+        pub amount_in: u128,
+        pub amount_out: u128,
+    }
+
+    impl Pair {
+        /// Constructor that creates a pair of `token0` and `token1`, or of
+        /// `token0` and the native currency if `token1` is `None`
+        #[ink(constructor)]
+        pub fn new(token0: Address, token1: Option<Address>) -> Self {
+            Self {
+                token0,
+                token1,
+                reserve0: 0,
+                reserve1: 0,
+                total_supply: 0,
+                balances: Mapping::default(),
+                allowances: Mapping::default(),
+                price0_cumulative: U256::zero(),
+                price1_cumulative: U256::zero(),
+                last_update: Self::env().block_timestamp(),
+                observation: (U256::zero(), U256::zero(), Self::env().block_timestamp()),
+                previous_observation: (U256::zero(), U256::zero(), Self::env().block_timestamp()),
+            }
+        }
+    }
+
+    impl Pair {
+        /// Returns the total LP share supply
+        #[ink(message, selector = 0x162DF8C2)]
+        pub fn total_supply(&self) -> u128 {
+            self.total_supply
+        }
+
+        /// Returns the LP share balance of the specified owner
+        #[ink(message, selector = 0x6568382F)]
+        pub fn balance_of(&self, owner: Address) -> u128 {
+            self.balances.get(owner).unwrap_or(0)
+        }
+
+        /// Returns the LP share allowance granted by owner to spender
+        #[ink(message, selector = 0x4D47D921)]
+        pub fn allowance(&self, owner: Address, spender: Address) -> u128 {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        /// Transfer LP shares from caller to recipient
+        #[ink(message, selector = 0xDB20F9F5)]
+        pub fn transfer(&mut self, to: Address, value: u128, _data: Vec<u8>) -> Result<(), PSP22Error> {
+            let from = self.env().caller();
+            self.transfer_shares(from, to, value)
+        }
+
+        /// Transfer LP shares from one account to another using allowance
+        #[ink(message, selector = 0x54B3C76E)]
+        pub fn transfer_from(
+            &mut self,
+            from: Address,
+            to: Address,
+            value: u128,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            self.spend_allowance(from, caller, value)?;
+            self.transfer_shares(from, to, value)
+        }
+
+        /// Approve spender to spend LP shares on behalf of caller
+        #[ink(message, selector = 0xB20F1BBD)]
+        pub fn approve(&mut self, spender: Address, value: u128) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), &value);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Returns the assets of the pair
+        #[ink(message)]
+        pub fn tokens(&self) -> (Address, Option<Address>) {
+            (self.token0, self.token1)
+        }
+
+        /// Returns the reserves of `token0` and `token1`
+        #[ink(message)]
+        pub fn get_reserves(&self) -> (u128, u128) {
+            (self.reserve0, self.reserve1)
+        }
+
+        /// Returns the output of swapping `amount_in` of `token0` for
+        /// `token1` (`zero_for_one`) or the other way around
+        #[ink(message)]
+        pub fn get_amount_out(&self, amount_in: u128, zero_for_one: bool) -> u128 {
+            let (reserve_in, reserve_out) = self.directed_reserves(zero_for_one);
+            get_amount_out(amount_in, reserve_in, reserve_out).unwrap_or(0)
+        }
+
+        /// Returns the cumulative prices of `token0` and `token1` as of now
+        #[ink(message)]
+        pub fn current_cumulative_prices(&self) -> (U256, U256) {
+            let elapsed = self.env().block_timestamp().saturating_sub(self.last_update);
+            if elapsed == 0 || self.reserve0 == 0 || self.reserve1 == 0 {
+                return (self.price0_cumulative, self.price1_cumulative);
+            }
+
+            let (price0, price1) = self.spot_prices();
+            let elapsed = U256::from(elapsed);
+            (
+                self.price0_cumulative.overflowing_add(price0 * elapsed).0,
+                self.price1_cumulative.overflowing_add(price1 * elapsed).0,
+            )
+        }
+
+        /// Returns the output of `amount_in` of `token` at the time-weighted
+        /// average price since the latest observation at least `TWAP_PERIOD`
+        /// old
+        ///
+        /// Observations are refreshed on reserve changes at most once per
+        /// `TWAP_PERIOD`, and the one they replace is kept, so right after a
+        /// refresh the average is still measured over a full period. Fails
+        /// until the pair is `TWAP_PERIOD` old. `token` is `None` for the
+        /// native currency.
+        #[ink(message)]
+        pub fn consult(&self, token: Option<Address>, amount_in: u128) -> Result<u128, PSP22Error> {
+            let now = self.env().block_timestamp();
+            let (observed0, observed1, observed_at) = if now.saturating_sub(self.observation.2) >= TWAP_PERIOD {
+                self.observation
+            } else {
+                self.previous_observation
+            };
+            let elapsed = now.saturating_sub(observed_at);
+            if elapsed < TWAP_PERIOD {
+                return Err(PSP22Error::Custom(String::from("Observation too recent")));
+            }
+
+            let (cumulative0, cumulative1) = self.current_cumulative_prices();
+            let delta = if token == Some(self.token0) {
+                cumulative0.overflowing_sub(observed0).0
+            } else if token == self.token1 {
+                cumulative1.overflowing_sub(observed1).0
+            } else {
+                return Err(PSP22Error::Custom(String::from("Invalid token")));
+            };
+
+            let average_price = delta / U256::from(elapsed);
+            let amount_out = average_price
+                .checked_mul(U256::from(amount_in))
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?
+                / Q112;
+            if amount_out > U256::from(u128::MAX) {
+                return Err(PSP22Error::Custom(String::from("Overflow")));
+            }
+
+            Ok(amount_out.low_u128())
+        }
+
+        /// Deposit both assets at the current ratio for LP shares
+        ///
+        /// At most the desired amounts are taken; the caller must have
+        /// approved the pair to spend them. For native pairs the transferred
+        /// value must equal `amount1_desired` and any unused part is refunded.
+        #[ink(message, payable)]
+        pub fn add_liquidity(
+            &mut self,
+            amount0_desired: u128,
+            amount1_desired: u128,
+            min_liquidity: u128,
+        ) -> Result<u128, PSP22Error> {
+            let sender = self.env().caller();
+            if self.token1.is_none() && self.native_value()? != amount1_desired {
+                return Err(PSP22Error::Custom(String::from("Value mismatch")));
+            }
+
+            let (amount0, amount1) = self.optimal_amounts(amount0_desired, amount1_desired)?;
+            let received0 = self.pull(Some(self.token0), sender, amount0)?;
+            let received1 = self.pull(self.token1, sender, amount1)?;
+            if self.token1.is_none() && amount1 < amount1_desired {
+                self.push(None, sender, amount1_desired - amount1)?;
+            }
+
+            let liquidity = if self.total_supply == 0 {
+                let root = (U256::from(received0) * U256::from(received1)).integer_sqrt();
+                let liquidity = root
+                    .low_u128()
+                    .checked_sub(MINIMUM_LIQUIDITY)
+                    .filter(|liquidity| *liquidity > 0)
+                    .ok_or(PSP22Error::Custom(String::from("Insufficient liquidity")))?;
+                self.mint_shares(self.env().address(), MINIMUM_LIQUIDITY)?;
+                liquidity
+            } else {
+                let by0 = U256::from(received0) * U256::from(self.total_supply) / U256::from(self.reserve0);
+                let by1 = U256::from(received1) * U256::from(self.total_supply) / U256::from(self.reserve1);
+                by0.min(by1).low_u128()
+            };
+            if liquidity == 0 || liquidity < min_liquidity {
+                return Err(PSP22Error::Custom(String::from("Slippage exceeded")));
+            }
+
+            self.mint_shares(sender, liquidity)?;
+            self.update_reserves(self.reserve0 + received0, self.reserve1 + received1);
+
+            self.env().emit_event(Mint {
+                sender,
+                amount0: received0,
+                amount1: received1,
+                liquidity,
+            });
+
+            Ok(liquidity)
+        }
+
+        /// Burn `liquidity` LP shares for the assets they represent
+        #[ink(message)]
+        pub fn remove_liquidity(
+            &mut self,
+            liquidity: u128,
+            min_amount0: u128,
+            min_amount1: u128,
+        ) -> Result<(u128, u128), PSP22Error> {
+            let sender = self.env().caller();
+            if self.total_supply == 0 {
+                return Err(PSP22Error::Custom(String::from("Insufficient liquidity")));
+            }
+
+            let amount0 = (U256::from(liquidity) * U256::from(self.reserve0) / U256::from(self.total_supply)).low_u128();
+            let amount1 = (U256::from(liquidity) * U256::from(self.reserve1) / U256::from(self.total_supply)).low_u128();
+            if amount0 < min_amount0 || amount1 < min_amount1 {
+                return Err(PSP22Error::Custom(String::from("Slippage exceeded")));
+            }
+
+            self.burn_shares(sender, liquidity)?;
+            self.update_reserves(self.reserve0 - amount0, self.reserve1 - amount1);
+
+            self.push(Some(self.token0), sender, amount0)?;
+            self.push(self.token1, sender, amount1)?;
+
+            self.env().emit_event(Burn {
+                sender,
+                amount0,
+                amount1,
+                liquidity,
+            });
+
+            Ok((amount0, amount1))
+        }
+
+        /// Swap `amount_in` of `token0` for `token1` (`zero_for_one`) or the
+        /// other way around, sending at least `min_amount_out` to `to`
+        ///
+        /// Native input is taken from the transferred value, which must equal
+        /// `amount_in`; token input is pulled with `transfer_from`.
+        #[ink(message, payable)]
+        pub fn swap(
+            &mut self,
+            zero_for_one: bool,
+            amount_in: u128,
+            min_amount_out: u128,
+            to: Address,
+        ) -> Result<u128, PSP22Error> {
+            let sender = self.env().caller();
+            let (token_in, token_out) = if zero_for_one {
+                (Some(self.token0), self.token1)
+            } else {
+                (self.token1, Some(self.token0))
+            };
+            if token_in.is_none() && self.native_value()? != amount_in {
+                return Err(PSP22Error::Custom(String::from("Value mismatch")));
+            }
+
+            let received = self.pull(token_in, sender, amount_in)?;
+            let (reserve_in, reserve_out) = self.directed_reserves(zero_for_one);
+            let amount_out = get_amount_out(received, reserve_in, reserve_out)
+                .ok_or(PSP22Error::Custom(String::from("Insufficient liquidity")))?;
+            if amount_out == 0 || amount_out < min_amount_out {
+                return Err(PSP22Error::Custom(String::from("Slippage exceeded")));
+            }
+
+            if zero_for_one {
+                self.update_reserves(self.reserve0 + received, self.reserve1 - amount_out);
+            } else {
+                self.update_reserves(self.reserve0 - amount_out, self.reserve1 + received);
+            }
+            self.push(token_out, to, amount_out)?;
+
+            self.env().emit_event(Swap {
+                sender,
+                to,
+                zero_for_one,
+                amount_in: received,
+                amount_out,
+            });
+
+            Ok(amount_out)
+        }
+    }
+
+    impl Pair {
+        /// Reserves ordered as (input, output) for the given swap direction
+        fn directed_reserves(&self, zero_for_one: bool) -> (u128, u128) {
+            if zero_for_one {
+                (self.reserve0, self.reserve1)
+            } else {
+                (self.reserve1, self.reserve0)
+            }
+        }
+
+        /// Current prices of `token0` in `token1` and the other way around, scaled by 2^112
+        fn spot_prices(&self) -> (U256, U256) {
+            let reserve0 = U256::from(self.reserve0);
+            let reserve1 = U256::from(self.reserve1);
+            (reserve1 * Q112 / reserve0, reserve0 * Q112 / reserve1)
+        }
+
+        /// Accumulate prices up to now, then store the new reserves
+        fn update_reserves(&mut self, reserve0: u128, reserve1: u128) {
+            let now = self.env().block_timestamp();
+
+            let (price0_cumulative, price1_cumulative) = self.current_cumulative_prices();
+            self.price0_cumulative = price0_cumulative;
+            self.price1_cumulative = price1_cumulative;
+            self.last_update = now;
+            self.reserve0 = reserve0;
+            self.reserve1 = reserve1;
+
+            if now.saturating_sub(self.observation.2) >= TWAP_PERIOD {
+                self.previous_observation = self.observation;
+                self.observation = (price0_cumulative, price1_cumulative, now);
+            }
+        }
+
+        /// Largest amounts not above the desired ones that match the reserve ratio
+        fn optimal_amounts(&self, amount0_desired: u128, amount1_desired: u128) -> Result<(u128, u128), PSP22Error> {
+            if self.reserve0 == 0 || self.reserve1 == 0 {
+                return Ok((amount0_desired, amount1_desired));
+            }
+
+            let amount1_optimal = U256::from(amount0_desired) * U256::from(self.reserve1) / U256::from(self.reserve0);
+            if amount1_optimal <= U256::from(amount1_desired) {
+                return Ok((amount0_desired, amount1_optimal.low_u128()));
+            }
+
+            let amount0_optimal = U256::from(amount1_desired) * U256::from(self.reserve0) / U256::from(self.reserve1);
+            Ok((amount0_optimal.low_u128(), amount1_desired))
+        }
+
+        /// Native value sent with the call
+        fn native_value(&self) -> Result<u128, PSP22Error> {
+            let value = self.env().transferred_value();
+            if value > U256::from(u128::MAX) {
+                return Err(PSP22Error::Custom(String::from("Overflow")));
+            }
+            Ok(value.low_u128())
+        }
+
+        /// Take `amount` of an asset from `from` and return what the pair received
+        ///
+        /// Token amounts are measured by balance so fee-on-transfer tokens are
+        /// credited correctly. Native amounts were already sent with the call.
+        fn pull(&self, token: Option<Address>, from: Address, amount: u128) -> Result<u128, PSP22Error> {
+            let Some(token) = token else {
+                return Ok(amount);
+            };
+            if amount == 0 {
+                return Ok(0);
+            }
+
+            let this = self.env().address();
+            let mut token: PspCoinRef = FromAddr::from_addr(token);
+            let before = token.balance_of(this);
+            token.transfer_from(from, this, amount, Vec::new())?;
+
+            Ok(token.balance_of(this).saturating_sub(before))
+        }
+
+        /// Send `amount` of an asset to `to`
+        fn push(&self, token: Option<Address>, to: Address, amount: u128) -> Result<(), PSP22Error> {
+            if amount == 0 {
+                return Ok(());
+            }
+
+            match token {
+                Some(token) => {
+                    let mut token: PspCoinRef = FromAddr::from_addr(token);
+                    token.transfer(to, amount, Vec::new())
+                }
+                None => self
+                    .env()
+                    .transfer(to, U256::from(amount))
+                    .map_err(|_| PSP22Error::Custom(String::from("Native transfer failed"))),
+            }
+        }
+
+        /// Consume `value` of the allowance `owner` granted to `spender`
+        fn spend_allowance(&mut self, owner: Address, spender: Address, value: u128) -> Result<(), PSP22Error> {
+            if owner == spender {
+                return Ok(());
+            }
+
+            let new_allowance = self
+                .allowance(owner, spender)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientAllowance)?;
+            self.allowances.insert((owner, spender), &new_allowance);
+
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value: new_allowance,
+            });
+
+            Ok(())
+        }
+
+        /// Move `value` LP shares between two accounts
+        fn transfer_shares(&mut self, from: Address, to: Address, value: u128) -> Result<(), PSP22Error> {
+            if from == to || value == 0 {
+                return Ok(());
+            }
+
+            let new_from_balance = self
+                .balance_of(from)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            let new_to_balance = self
+                .balance_of(to)
+                .checked_add(value)
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+
+            self.balances.insert(from, &new_from_balance);
+            self.balances.insert(to, &new_to_balance);
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Create `value` new LP shares on `to`'s account
+        fn mint_shares(&mut self, to: Address, value: u128) -> Result<(), PSP22Error> {
+            self.total_supply = self
+                .total_supply
+                .checked_add(value)
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+            self.balances.insert(to, &(self.balance_of(to) + value));
+
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Destroy `value` LP shares held by `from`
+        fn burn_shares(&mut self, from: Address, value: u128) -> Result<(), PSP22Error> {
+            let new_balance = self
+                .balance_of(from)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.balances.insert(from, &new_balance);
+            self.total_supply -= value;
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: None,
+                value,
+            });
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn get_amount_out_charges_fee() {
+            // 1000 in against 10_000/10_000 reserves, minus 0.3%
+            assert_eq!(get_amount_out(1_000, 10_000, 10_000), Some(906));
+            assert_eq!(get_amount_out(0, 10_000, 10_000), None);
+            assert_eq!(get_amount_out(1_000, 0, 10_000), None);
+        }
+
+        #[ink::test]
+        fn optimal_amounts_follow_reserve_ratio() {
+            let accounts = ink::env::test::default_accounts();
+            let mut pair = Pair::new(accounts.django, None);
+
+            assert_eq!(pair.optimal_amounts(100, 50), Ok((100, 50)));
+
+            pair.reserve0 = 1_000;
+            pair.reserve1 = 2_000;
+            assert_eq!(pair.optimal_amounts(100, 500), Ok((100, 200)));
+            assert_eq!(pair.optimal_amounts(100, 100), Ok((50, 100)));
+        }
+
+        #[ink::test]
+        fn consult_returns_time_weighted_price() {
+            let accounts = ink::env::test::default_accounts();
+            let mut pair = Pair::new(accounts.django, None);
+            assert_eq!(
+                pair.consult(Some(accounts.django), 10),
+                Err(PSP22Error::Custom(String::from("Observation too recent")))
+            );
+
+            // One token0 is worth two token1 for a period, then half a token1
+            pair.update_reserves(1_000, 2_000);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(TWAP_PERIOD - 1);
+            assert_eq!(
+                pair.consult(Some(accounts.django), 10),
+                Err(PSP22Error::Custom(String::from("Observation too recent")))
+            );
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(TWAP_PERIOD);
+            assert_eq!(pair.consult(Some(accounts.django), 10), Ok(20));
+
+            pair.update_reserves(2_000, 1_000);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2 * TWAP_PERIOD);
+            assert_eq!(pair.consult(Some(accounts.django), 10), Ok(5));
+            assert_eq!(pair.consult(None, 10), Ok(20));
+            assert_eq!(
+                pair.consult(Some(accounts.eve), 10),
+                Err(PSP22Error::Custom(String::from("Invalid token")))
+            );
+        }
+
+        #[ink::test]
+        fn consult_right_after_observation_refresh_spans_a_full_period() {
+            let accounts = ink::env::test::default_accounts();
+            let mut pair = Pair::new(accounts.django, None);
+            pair.update_reserves(1_000, 2_000);
+
+            // The swap refreshing the observation moves the spot price to a
+            // quarter, but the average still covers the whole last period
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(TWAP_PERIOD);
+            pair.update_reserves(4_000, 1_000);
+            assert_eq!(pair.observation.2, TWAP_PERIOD);
+            assert_eq!(pair.consult(Some(accounts.django), 10), Ok(20));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(TWAP_PERIOD + 1);
+            assert_eq!(pair.consult(Some(accounts.django), 100), Ok(199));
+        }
+
+        #[ink::test]
+        fn lp_share_transfers_work() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut pair = Pair::new(accounts.django, None);
+            assert_eq!(pair.mint_shares(accounts.alice, 100), Ok(()));
+
+            assert_eq!(pair.approve(accounts.bob, 40), Ok(()));
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(
+                pair.transfer_from(accounts.alice, accounts.charlie, 40, vec![]),
+                Ok(())
+            );
+            assert_eq!(
+                pair.transfer_from(accounts.alice, accounts.charlie, 1, vec![]),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(pair.balance_of(accounts.charlie), 40);
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::PspCoin;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn native_pair_adds_swaps_and_removes<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a token/native pair seeded by alice
+            let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+            let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+            let mut token_constructor = PspCoinRef::new_with_supply(1_000_000);
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut pair_constructor = PairRef::new(token.addr, None);
+            let pair = client
+                .instantiate("pair", &ink_e2e::alice(), &mut pair_constructor)
+                .submit()
+                .await
+                .expect("pair instantiate failed");
+            let mut pair_call = pair.call_builder::<Pair>();
+
+            client
+                .call(&ink_e2e::alice(), &token_call.approve(pair.addr, 10_000))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::alice(), &pair_call.add_liquidity(10_000, 10_000, 0))
+                .value(10_000)
+                .submit()
+                .await?;
+
+            let shares = client
+                .call(&ink_e2e::alice(), &pair_call.balance_of(alice))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(shares, 10_000 - MINIMUM_LIQUIDITY);
+
+            // When bob swaps native currency for tokens
+            client
+                .call(&ink_e2e::bob(), &pair_call.swap(false, 1_000, 900, bob))
+                .value(1_000)
+                .submit()
+                .await?;
+
+            // Then bob received the constant-product output minus the fee
+            let balance = client
+                .call(&ink_e2e::bob(), &token_call.balance_of(bob))
+                .dry_run()
+                .await?;
+            assert_eq!(balance.return_value(), 906);
+
+            let reserves = client
+                .call(&ink_e2e::bob(), &pair_call.get_reserves())
+                .dry_run()
+                .await?;
+            assert_eq!(reserves.return_value(), (9_094, 11_000));
+
+            // And alice can withdraw her share of the grown pool
+            let removed = client
+                .call(&ink_e2e::alice(), &pair_call.remove_liquidity(shares, 0, 0))
+                .submit()
+                .await?
+                .return_value();
+            assert_eq!(removed, Ok((8_184, 9_900)));
+
+            Ok(())
+        }
+    }
+}
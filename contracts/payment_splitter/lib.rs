@@ -0,0 +1,309 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::payment_splitter::PaymentSplitterRef;
+
+#[ink::contract]
+mod payment_splitter {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use ink::env::call::FromAddr;
+    use ink::U256;
+    use psp_coin::{PSP22Error, PspCoinRef};
+
+    /// Storage structure for the payment splitter
+    ///
+    /// Tokens and native currency sent to the splitter are owed to the payees
+    /// in proportion to their shares; each payee pulls its part with `release`.
+    #[ink(storage)]
+    pub struct PaymentSplitter {
+        /// Token whose balance is split among the payees
+        token: Address,
+        /// Accounts receiving a part of the payments
+        payees: Vec<Address>,
+        /// Shares held by each payee
+        shares: Mapping<Address, u128>,
+        /// Sum of all shares
+        total_shares: u128,
+        /// Native currency released to each payee
+        released_native: Mapping<Address, U256>,
+        /// Sum of all native currency released
+        total_released_native: U256,
+        /// Tokens released to each payee
+        released_tokens: Mapping<Address, u128>,
+        /// Sum of all tokens released
+        total_released_tokens: u128,
+    }
+
+    /// Event emitted when native currency is paid into the splitter
+    #[ink(event)]
+    pub struct PaymentReceived {
+        #[ink(topic)]
+        pub from: Address,
+        pub amount: U256,
+    }
+
+    /// Event emitted when a payee's part is released
+    #[ink(event)]
+    pub struct PaymentReleased {
+        #[ink(topic)]
+        pub payee: Address,
+        pub native: U256,
+        pub tokens: u128,
+    }
+
+    impl PaymentSplitter {
+        /// Constructor that splits payments in `token` and native currency
+        /// among `payees` according to `shares`
+        #[ink(constructor)]
+        pub fn new(token: Address, payees: Vec<Address>, shares: Vec<u128>) -> Result<Self, PSP22Error> {
+            if payees.is_empty() || payees.len() != shares.len() {
+                return Err(PSP22Error::Custom(String::from("Payees and shares mismatch")));
+            }
+
+            let mut instance = Self {
+                token,
+                payees: Vec::new(),
+                shares: Mapping::default(),
+                total_shares: 0,
+                released_native: Mapping::default(),
+                total_released_native: U256::zero(),
+                released_tokens: Mapping::default(),
+                total_released_tokens: 0,
+            };
+
+            for (payee, share) in payees.into_iter().zip(shares) {
+                if share == 0 {
+                    return Err(PSP22Error::Custom(String::from("Zero shares")));
+                }
+                if instance.shares.contains(payee) {
+                    return Err(PSP22Error::Custom(String::from("Duplicate payee")));
+                }
+
+                instance.shares.insert(payee, &share);
+                instance.payees.push(payee);
+                instance.total_shares = instance
+                    .total_shares
+                    .checked_add(share)
+                    .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+            }
+
+            Ok(instance)
+        }
+    }
+
+    impl PaymentSplitter {
+        /// Returns the token split among the payees
+        #[ink(message)]
+        pub fn token(&self) -> Address {
+            self.token
+        }
+
+        /// Returns the payees
+        #[ink(message)]
+        pub fn payees(&self) -> Vec<Address> {
+            self.payees.clone()
+        }
+
+        /// Returns the shares held by `payee`
+        #[ink(message)]
+        pub fn shares(&self, payee: Address) -> u128 {
+            self.shares.get(payee).unwrap_or(0)
+        }
+
+        /// Returns the sum of all shares
+        #[ink(message)]
+        pub fn total_shares(&self) -> u128 {
+            self.total_shares
+        }
+
+        /// Returns the native currency already released to `payee`
+        #[ink(message)]
+        pub fn released_native(&self, payee: Address) -> U256 {
+            self.released_native.get(payee).unwrap_or_default()
+        }
+
+        /// Returns the tokens already released to `payee`
+        #[ink(message)]
+        pub fn released_tokens(&self, payee: Address) -> u128 {
+            self.released_tokens.get(payee).unwrap_or(0)
+        }
+
+        /// Returns the native currency `payee` can currently release
+        #[ink(message)]
+        pub fn releasable_native(&self, payee: Address) -> U256 {
+            let total_received = self.env().balance().saturating_add(self.total_released_native);
+            let owed = total_received.saturating_mul(U256::from(self.shares(payee)))
+                / U256::from(self.total_shares);
+            owed.saturating_sub(self.released_native(payee))
+        }
+
+        /// Returns the tokens `payee` can currently release
+        #[ink(message)]
+        pub fn releasable_tokens(&self, payee: Address) -> u128 {
+            let token: PspCoinRef = FromAddr::from_addr(self.token);
+            let total_received = U256::from(token.balance_of(self.env().address()))
+                .saturating_add(U256::from(self.total_released_tokens));
+            let owed = total_received.saturating_mul(U256::from(self.shares(payee)))
+                / U256::from(self.total_shares);
+            owed.low_u128().saturating_sub(self.released_tokens(payee))
+        }
+
+        /// Accept native currency to be split among the payees
+        #[ink(message, payable)]
+        pub fn receive(&mut self) {
+            self.env().emit_event(PaymentReceived {
+                from: self.env().caller(),
+                amount: self.env().transferred_value(),
+            });
+        }
+
+        /// Pay out everything `payee` is owed in tokens and native currency
+        ///
+        /// Anyone may call this on behalf of a payee; funds always go to the
+        /// payee itself.
+        #[ink(message)]
+        pub fn release(&mut self, payee: Address) -> Result<(), PSP22Error> {
+            if !self.shares.contains(payee) {
+                return Err(PSP22Error::Custom(String::from("Not a payee")));
+            }
+
+            let native = self.releasable_native(payee);
+            let tokens = self.releasable_tokens(payee);
+            if native.is_zero() && tokens == 0 {
+                return Err(PSP22Error::Custom(String::from("Nothing to release")));
+            }
+
+            // Record the release before paying out
+            self.released_native
+                .insert(payee, &self.released_native(payee).saturating_add(native));
+            self.total_released_native = self.total_released_native.saturating_add(native);
+            self.released_tokens
+                .insert(payee, &self.released_tokens(payee).saturating_add(tokens));
+            self.total_released_tokens = self.total_released_tokens.saturating_add(tokens);
+
+            if !native.is_zero() {
+                self.env()
+                    .transfer(payee, native)
+                    .map_err(|_| PSP22Error::Custom(String::from("Native transfer failed")))?;
+            }
+            if tokens > 0 {
+                let mut token: PspCoinRef = FromAddr::from_addr(self.token);
+                token.transfer(payee, tokens, Vec::new())?;
+            }
+
+            self.env().emit_event(PaymentReleased {
+                payee,
+                native,
+                tokens,
+            });
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn new_validates_payees_and_shares() {
+            let accounts = ink::env::test::default_accounts();
+
+            assert_eq!(
+                PaymentSplitter::new(accounts.django, vec![accounts.alice], vec![]).err(),
+                Some(PSP22Error::Custom(String::from("Payees and shares mismatch")))
+            );
+            assert_eq!(
+                PaymentSplitter::new(accounts.django, vec![accounts.alice], vec![0]).err(),
+                Some(PSP22Error::Custom(String::from("Zero shares")))
+            );
+            assert_eq!(
+                PaymentSplitter::new(
+                    accounts.django,
+                    vec![accounts.alice, accounts.alice],
+                    vec![1, 1]
+                )
+                .err(),
+                Some(PSP22Error::Custom(String::from("Duplicate payee")))
+            );
+        }
+
+        #[ink::test]
+        fn native_payments_are_split_by_shares() {
+            let accounts = ink::env::test::default_accounts();
+
+            let splitter = PaymentSplitter::new(
+                accounts.django,
+                vec![accounts.alice, accounts.bob],
+                vec![1, 3],
+            )
+            .expect("valid payees");
+            assert_eq!(splitter.total_shares(), 4);
+
+            let this = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_contract_balance(this, U256::from(400));
+            assert_eq!(splitter.releasable_native(accounts.alice), U256::from(100));
+            assert_eq!(splitter.releasable_native(accounts.bob), U256::from(300));
+            assert_eq!(splitter.releasable_native(accounts.charlie), U256::zero());
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::PspCoin;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn token_payments_are_released_by_shares<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a splitter paying bob one share and charlie three
+            let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+            let charlie = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::charlie());
+
+            let mut token_constructor = PspCoinRef::new_with_supply(1_000_000);
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut splitter_constructor =
+                PaymentSplitterRef::new(token.addr, vec![bob, charlie], vec![1, 3]);
+            let splitter = client
+                .instantiate("payment_splitter", &ink_e2e::alice(), &mut splitter_constructor)
+                .submit()
+                .await
+                .expect("splitter instantiate failed");
+            let mut splitter_call = splitter.call_builder::<PaymentSplitter>();
+
+            // When tokens are paid in and charlie's part is released
+            client
+                .call(&ink_e2e::alice(), &token_call.transfer(splitter.addr, 400, Vec::new()))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::alice(), &splitter_call.release(charlie))
+                .submit()
+                .await?;
+
+            // Then charlie received three quarters and bob's part is still owed
+            let balance = client
+                .call(&ink_e2e::alice(), &token_call.balance_of(charlie))
+                .dry_run()
+                .await?;
+            assert_eq!(balance.return_value(), 300);
+
+            let releasable = client
+                .call(&ink_e2e::alice(), &splitter_call.releasable_tokens(bob))
+                .dry_run()
+                .await?;
+            assert_eq!(releasable.return_value(), 100);
+
+            Ok(())
+        }
+    }
+}
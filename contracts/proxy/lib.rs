@@ -0,0 +1,241 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::proxy::ProxyRef;
+
+/// Storage key of the proxy admin, outside the implementation's root storage
+pub const ADMIN_KEY: u32 = 0x5052_5841;
+
+/// Storage key of the implementation address, outside the implementation's root storage
+pub const IMPLEMENTATION_KEY: u32 = 0x5052_5849;
+
+#[ink::contract]
+mod proxy {
+    use ink::env::{call::build_call, CallFlags, DefaultEnvironment};
+    use ink::storage::{traits::ManualKey, Lazy};
+
+    use crate::{ADMIN_KEY, IMPLEMENTATION_KEY};
+
+    /// Storage structure for the delegating proxy
+    ///
+    /// Every call that does not match one of the proxy's own selectors is
+    /// delegated to the implementation, which runs against this contract's
+    /// storage. Proxy state lives under manual keys so it never overlaps
+    /// the implementation's root storage, and the proxy's own messages take
+    /// `&self` so dispatch never writes the (empty) proxy root over it.
+    ///
+    /// A constructor cannot run through a delegate call, so an existing
+    /// deployment is moved behind a proxy by writing `ADMIN_KEY` and
+    /// `IMPLEMENTATION_KEY` and switching its code to this contract (see
+    /// the token's `upgrade_to_proxy`).
+    #[ink(storage)]
+    pub struct Proxy {
+        /// Account allowed to change the implementation
+        admin: Lazy<Address, ManualKey<ADMIN_KEY>>,
+        /// Contract whose code handles delegated calls
+        implementation: Lazy<Address, ManualKey<IMPLEMENTATION_KEY>>,
+    }
+
+    /// Event emitted when the implementation changes
+    #[ink(event)]
+    pub struct ImplementationChanged {
+        #[ink(topic)]
+        pub implementation: Address,
+    }
+
+    /// Event emitted when the proxy admin changes
+    #[ink(event)]
+    pub struct AdminChanged {
+        #[ink(topic)]
+        pub admin: Address,
+    }
+
+    /// Errors returned by the proxy's own messages
+    #[derive(Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum ProxyError {
+        /// Caller is not the proxy admin
+        Unauthorized,
+    }
+
+    impl Proxy {
+        /// Constructor that delegates to `implementation`, with the caller as admin
+        #[ink(constructor)]
+        pub fn new(implementation: Address) -> Self {
+            let mut proxy = Self {
+                admin: Lazy::new(),
+                implementation: Lazy::new(),
+            };
+            proxy.admin.set(&Self::env().caller());
+            proxy.implementation.set(&implementation);
+            proxy
+        }
+    }
+
+    impl Proxy {
+        /// Returns the proxy admin
+        #[ink(message, selector = 0xC0DE0001)]
+        pub fn proxy_admin(&self) -> Option<Address> {
+            self.admin.get()
+        }
+
+        /// Returns the contract whose code handles delegated calls
+        #[ink(message, selector = 0xC0DE0002)]
+        pub fn proxy_implementation(&self) -> Option<Address> {
+            self.implementation.get()
+        }
+
+        /// Delegate all further calls to `implementation` (admin only)
+        #[ink(message, selector = 0xC0DE0003)]
+        pub fn set_implementation(&self, implementation: Address) -> Result<(), ProxyError> {
+            self.ensure_admin()?;
+            ink::env::set_contract_storage(&IMPLEMENTATION_KEY, &implementation);
+            self.env().emit_event(ImplementationChanged { implementation });
+            Ok(())
+        }
+
+        /// Hand the admin role to `admin` (admin only)
+        #[ink(message, selector = 0xC0DE0004)]
+        pub fn set_proxy_admin(&self, admin: Address) -> Result<(), ProxyError> {
+            self.ensure_admin()?;
+            ink::env::set_contract_storage(&ADMIN_KEY, &admin);
+            self.env().emit_event(AdminChanged { admin });
+            Ok(())
+        }
+
+        /// Delegate any other call to the implementation
+        #[ink(message, payable, selector = _)]
+        pub fn forward(&self) {
+            let implementation = self
+                .implementation
+                .get()
+                .unwrap_or_else(|| panic!("proxy implementation not set"));
+
+            build_call::<DefaultEnvironment>()
+                .delegate(implementation)
+                .call_flags(CallFlags::FORWARD_INPUT | CallFlags::TAIL_CALL)
+                .invoke();
+            unreachable!("a tail call never returns to the proxy");
+        }
+    }
+
+    impl Proxy {
+        /// Fails unless the caller is the proxy admin
+        fn ensure_admin(&self) -> Result<(), ProxyError> {
+            if self.admin.get() != Some(self.env().caller()) {
+                return Err(ProxyError::Unauthorized);
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn only_admin_changes_implementation() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let proxy = Proxy::new(accounts.django);
+            assert_eq!(proxy.proxy_admin(), Some(accounts.alice));
+            assert_eq!(proxy.proxy_implementation(), Some(accounts.django));
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(proxy.set_implementation(accounts.eve), Err(ProxyError::Unauthorized));
+            assert_eq!(proxy.set_proxy_admin(accounts.bob), Err(ProxyError::Unauthorized));
+
+            ink::env::test::set_caller(accounts.alice);
+            assert_eq!(proxy.set_implementation(accounts.eve), Ok(()));
+            assert_eq!(proxy.proxy_implementation(), Some(accounts.eve));
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::{PspCoin, PspCoinRef};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn token_keeps_state_behind_proxy_across_upgrades<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a token with balances and two deployments of its code
+            let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+            let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+            let mut token_constructor = PspCoinRef::new_with_supply(1_000);
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut implementations = Vec::new();
+            for _ in 0..2 {
+                let mut implementation_constructor = PspCoinRef::new();
+                let implementation = client
+                    .instantiate("psp_coin", &ink_e2e::alice(), &mut implementation_constructor)
+                    .submit()
+                    .await
+                    .expect("implementation instantiate failed");
+                implementations.push(implementation.addr);
+            }
+
+            let proxy_code = client
+                .upload("proxy", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("proxy upload failed")
+                .code_hash;
+
+            // When the token is moved behind the proxy
+            client
+                .call(
+                    &ink_e2e::alice(),
+                    &token_call.upgrade_to_proxy(proxy_code, implementations[0]),
+                )
+                .submit()
+                .await?;
+            let mut proxy_call = token.call_builder::<Proxy>();
+
+            // Then token calls are delegated against the existing storage
+            client
+                .call(&ink_e2e::alice(), &token_call.transfer(bob, 100, Vec::new()))
+                .submit()
+                .await?;
+            let admin = client
+                .call(&ink_e2e::alice(), &proxy_call.proxy_admin())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(admin, Some(alice));
+
+            // And switching the implementation keeps all balances
+            let upgraded = client
+                .call(&ink_e2e::alice(), &proxy_call.set_implementation(implementations[1]))
+                .submit()
+                .await?
+                .return_value();
+            assert_eq!(upgraded, Ok(()));
+
+            let balance = client
+                .call(&ink_e2e::bob(), &token_call.balance_of(bob))
+                .dry_run()
+                .await?;
+            assert_eq!(balance.return_value(), 100);
+            let implementation = client
+                .call(&ink_e2e::alice(), &proxy_call.proxy_implementation())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(implementation, Some(implementations[1]));
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,317 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::rebasing_token::RebasingTokenRef;
+
+#[ink::contract]
+mod rebasing_token {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use psp_coin::PSP22Error;
+
+    /// Storage structure for the elastic-supply token
+    ///
+    /// Balances are stored in gons, a fixed internal unit of which there
+    /// are always `total_gons`. A rebase only changes how many gons make up
+    /// one token (fragment), so every balance scales proportionally while
+    /// transfers move exact multiples of `gons_per_fragment`.
+    #[ink(storage)]
+    pub struct RebasingToken {
+        /// Account allowed to set the oracle and rebase
+        owner: Address,
+        /// Optional account also allowed to rebase
+        oracle: Option<Address>,
+        /// Total supply in fragments
+        total_supply: u128,
+        /// Fixed number of gons, a multiple of the initial supply
+        total_gons: u128,
+        /// Gons per fragment at the current supply
+        gons_per_fragment: u128,
+        /// Mapping from account to balance in gons
+        gon_balances: Mapping<Address, u128>,
+        /// Nested mapping for allowances in fragments (owner, spender) -> amount
+        allowances: Mapping<(Address, Address), u128>,
+        /// Number of rebases so far
+        epoch: u64,
+    }
+
+    /// Event emitted when tokens are transferred, in fragments
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        pub from: Option<Address>,
+        #[ink(topic)]
+        pub to: Option<Address>,
+        pub value: u128,
+    }
+
+    /// Event emitted when an approval is granted
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        pub owner: Address,
+        #[ink(topic)]
+        pub spender: Address,
+        pub value: u128,
+    }
+
+    /// Event emitted when the supply is rebased
+    #[ink(event)]
+    pub struct Rebase {
+        #[ink(topic)]
+        pub epoch: u64,
+        pub delta: i128,
+        pub total_supply: u128,
+    }
+
+    impl RebasingToken {
+        /// Constructor that mints `initial_supply` to the caller
+        #[ink(constructor)]
+        pub fn new(initial_supply: u128) -> Result<Self, PSP22Error> {
+            if initial_supply == 0 {
+                return Err(PSP22Error::Custom(String::from("Zero supply")));
+            }
+
+            let caller = Self::env().caller();
+            let total_gons = u128::MAX - u128::MAX % initial_supply;
+
+            let mut gon_balances = Mapping::default();
+            gon_balances.insert(caller, &total_gons);
+
+            Self::env().emit_event(Transfer {
+                from: None,
+                to: Some(caller),
+                value: initial_supply,
+            });
+
+            Ok(Self {
+                owner: caller,
+                oracle: None,
+                total_supply: initial_supply,
+                total_gons,
+                gons_per_fragment: total_gons / initial_supply,
+                gon_balances,
+                allowances: Mapping::default(),
+                epoch: 0,
+            })
+        }
+    }
+
+    impl RebasingToken {
+        /// Returns the total supply in fragments
+        #[ink(message, selector = 0x162DF8C2)]
+        pub fn total_supply(&self) -> u128 {
+            self.total_supply
+        }
+
+        /// Returns the balance of `owner` in fragments
+        #[ink(message, selector = 0x6568382F)]
+        pub fn balance_of(&self, owner: Address) -> u128 {
+            self.scaled_balance_of(owner) / self.gons_per_fragment
+        }
+
+        /// Returns the balance of `owner` in gons, which rebases never change
+        #[ink(message)]
+        pub fn scaled_balance_of(&self, owner: Address) -> u128 {
+            self.gon_balances.get(owner).unwrap_or(0)
+        }
+
+        /// Returns the fixed total supply in gons
+        #[ink(message)]
+        pub fn scaled_total_supply(&self) -> u128 {
+            self.total_gons
+        }
+
+        /// Returns the number of gons per fragment
+        #[ink(message)]
+        pub fn gons_per_fragment(&self) -> u128 {
+            self.gons_per_fragment
+        }
+
+        /// Returns the number of rebases so far
+        #[ink(message)]
+        pub fn epoch(&self) -> u64 {
+            self.epoch
+        }
+
+        /// Returns the rebase oracle, if any
+        #[ink(message)]
+        pub fn oracle(&self) -> Option<Address> {
+            self.oracle
+        }
+
+        /// Returns the allowance granted by owner to spender
+        #[ink(message, selector = 0x4D47D921)]
+        pub fn allowance(&self, owner: Address, spender: Address) -> u128 {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        /// Transfer `value` fragments from caller to recipient
+        #[ink(message, selector = 0xDB20F9F5)]
+        pub fn transfer(&mut self, to: Address, value: u128, _data: Vec<u8>) -> Result<(), PSP22Error> {
+            let from = self.env().caller();
+            self.transfer_fragments(from, to, value)
+        }
+
+        /// Transfer `value` fragments from one account to another using allowance
+        #[ink(message, selector = 0x54B3C76E)]
+        pub fn transfer_from(
+            &mut self,
+            from: Address,
+            to: Address,
+            value: u128,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            let allowance = self.allowance(from, caller);
+            if allowance < value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+
+            self.transfer_fragments(from, to, value)?;
+            self.allowances.insert((from, caller), &(allowance - value));
+            Ok(())
+        }
+
+        /// Approve spender to spend `value` fragments on behalf of caller
+        #[ink(message, selector = 0xB20F1BBD)]
+        pub fn approve(&mut self, spender: Address, value: u128) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), &value);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Set the account allowed to rebase besides the owner (owner only)
+        #[ink(message)]
+        pub fn set_oracle(&mut self, oracle: Option<Address>) -> Result<(), PSP22Error> {
+            if self.env().caller() != self.owner {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            self.oracle = oracle;
+            Ok(())
+        }
+
+        /// Grow or shrink the supply by `delta` fragments, scaling every
+        /// balance proportionally (owner or oracle only)
+        ///
+        /// The supply must stay above zero and at most the number of gons.
+        #[ink(message)]
+        pub fn rebase(&mut self, delta: i128) -> Result<u128, PSP22Error> {
+            let caller = self.env().caller();
+            if caller != self.owner && Some(caller) != self.oracle {
+                return Err(PSP22Error::Unauthorized);
+            }
+
+            let total_supply = if delta < 0 {
+                self.total_supply.checked_sub(delta.unsigned_abs())
+            } else {
+                self.total_supply.checked_add(delta.unsigned_abs())
+            }
+            .filter(|supply| *supply > 0 && *supply <= self.total_gons)
+            .ok_or(PSP22Error::Custom(String::from("Invalid rebase")))?;
+
+            self.total_supply = total_supply;
+            self.gons_per_fragment = self.total_gons / total_supply;
+            self.epoch += 1;
+
+            self.env().emit_event(Rebase {
+                epoch: self.epoch,
+                delta,
+                total_supply,
+            });
+
+            Ok(total_supply)
+        }
+    }
+
+    impl RebasingToken {
+        /// Move exactly `value` fragments worth of gons between accounts
+        fn transfer_fragments(&mut self, from: Address, to: Address, value: u128) -> Result<(), PSP22Error> {
+            let gons = value
+                .checked_mul(self.gons_per_fragment)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+
+            let new_from_balance = self
+                .scaled_balance_of(from)
+                .checked_sub(gons)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.gon_balances.insert(from, &new_from_balance);
+            self.gon_balances.insert(to, &(self.scaled_balance_of(to) + gons));
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn rebase_scales_balances_proportionally() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut token = RebasingToken::new(1_000).unwrap();
+            assert_eq!(token.transfer(accounts.bob, 250, Vec::new()), Ok(()));
+
+            assert_eq!(token.rebase(1_000), Ok(2_000));
+            assert_eq!(token.epoch(), 1);
+            assert_eq!(token.balance_of(accounts.alice), 1_500);
+            assert_eq!(token.balance_of(accounts.bob), 500);
+
+            assert_eq!(token.rebase(-1_500), Ok(500));
+            assert_eq!(token.balance_of(accounts.alice), 375);
+            assert_eq!(token.balance_of(accounts.bob), 125);
+            assert_eq!(
+                token.scaled_balance_of(accounts.bob),
+                token.scaled_total_supply() / 4
+            );
+        }
+
+        #[ink::test]
+        fn transfers_are_exact_after_rebase() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut token = RebasingToken::new(1_000).unwrap();
+            assert_eq!(token.rebase(333), Ok(1_333));
+
+            assert_eq!(token.transfer(accounts.bob, 77, Vec::new()), Ok(()));
+            assert_eq!(token.balance_of(accounts.bob), 77);
+            assert_eq!(token.balance_of(accounts.alice), 1_256);
+            assert_eq!(
+                token.transfer(accounts.bob, 1_257, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn rebase_is_restricted_and_bounded() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut token = RebasingToken::new(1_000).unwrap();
+            assert_eq!(
+                token.rebase(-1_000),
+                Err(PSP22Error::Custom(String::from("Invalid rebase")))
+            );
+            assert_eq!(token.set_oracle(Some(accounts.charlie)), Ok(()));
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(token.rebase(10), Err(PSP22Error::Unauthorized));
+
+            ink::env::test::set_caller(accounts.charlie);
+            assert_eq!(token.rebase(10), Ok(1_010));
+        }
+    }
+}
@@ -0,0 +1,192 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::reentrant_receiver::ReentrantReceiverRef;
+
+#[ink::contract]
+mod reentrant_receiver {
+    use ink::prelude::vec::Vec;
+    use ink::env::{
+        call::{build_call, ExecutionInput, Selector},
+        DefaultEnvironment,
+    };
+    use psp_coin::PSP22Error;
+
+    /// Selector of the token's `transfer_and_call(to, value, data)`
+    const TRANSFER_AND_CALL_SELECTOR: [u8; 4] = [0xac, 0xd1, 0x0e, 0x50];
+
+    /// Storage structure for the reentrant receiver
+    ///
+    /// A hostile `transfer_and_call` recipient used to test how the token
+    /// handles re-entry: on every callback it calls `transfer_and_call`
+    /// back on the token and records the outcome, accepting the transfer
+    /// either way. The token calls receivers without allowing re-entry, so
+    /// the runtime refuses that call before it reaches the token at all.
+    #[ink(storage)]
+    pub struct ReentrantReceiver {
+        /// Number of callbacks received
+        callbacks: u32,
+        /// Whether the runtime refused the last re-entrant call
+        last_reentry_refused: bool,
+        /// Error code the token returned for the last re-entrant call, if
+        /// the call reached it and failed
+        last_reentry_error: Option<u32>,
+    }
+
+    impl ReentrantReceiver {
+        /// Constructor that starts with no recorded callbacks
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                callbacks: 0,
+                last_reentry_refused: false,
+                last_reentry_error: None,
+            }
+        }
+    }
+
+    impl ReentrantReceiver {
+        /// Returns the number of callbacks received
+        #[ink(message)]
+        pub fn callbacks(&self) -> u32 {
+            self.callbacks
+        }
+
+        /// Returns whether the runtime refused the last re-entrant call
+        #[ink(message)]
+        pub fn last_reentry_refused(&self) -> bool {
+            self.last_reentry_refused
+        }
+
+        /// Returns the error code the token returned for the last
+        /// re-entrant call, if it reached the token and failed
+        #[ink(message)]
+        pub fn last_reentry_error(&self) -> Option<u32> {
+            self.last_reentry_error
+        }
+
+        /// Callback invoked by the token's `transfer_and_call`; tries to send
+        /// the tokens straight back through `transfer_and_call`
+        #[ink(message, selector = 0x641D7C83)]
+        pub fn on_transfer_received(
+            &mut self,
+            _operator: Address,
+            from: Address,
+            value: u128,
+            data: Vec<u8>,
+        ) -> Result<(), u32> {
+            self.callbacks = self.callbacks.saturating_add(1);
+
+            let reentry = build_call::<DefaultEnvironment>()
+                .call(self.env().caller())
+                .exec_input(
+                    ExecutionInput::new(Selector::new(TRANSFER_AND_CALL_SELECTOR))
+                        .push_arg(from)
+                        .push_arg(value)
+                        .push_arg(data),
+                )
+                .returns::<Result<(), PSP22Error>>()
+                .try_invoke();
+
+            self.last_reentry_refused = reentry.is_err();
+            self.last_reentry_error = match reentry {
+                Ok(Ok(Err(error))) => Some(u32::from(error)),
+                _ => None,
+            };
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn new_receiver_has_no_callbacks() {
+            let receiver = ReentrantReceiver::new();
+
+            assert_eq!(receiver.callbacks(), 0);
+            assert!(!receiver.last_reentry_refused());
+            assert_eq!(receiver.last_reentry_error(), None);
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::{PspCoin, PspCoinRef};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn reentrant_callback_is_rejected<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a token and a receiver that re-enters it on every callback
+            let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+
+            let mut token_constructor = PspCoinRef::new_with_supply(1_000);
+            let token = client
+                .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_call = token.call_builder::<PspCoin>();
+
+            let mut receiver_constructor = ReentrantReceiverRef::new();
+            let receiver = client
+                .instantiate("reentrant_receiver", &ink_e2e::alice(), &mut receiver_constructor)
+                .submit()
+                .await
+                .expect("receiver instantiate failed");
+            let receiver_call = receiver.call_builder::<ReentrantReceiver>();
+
+            // When alice sends it tokens through transfer_and_call
+            let sent = client
+                .call(
+                    &ink_e2e::alice(),
+                    &token_call.transfer_and_call(receiver.addr, 100, Vec::new()),
+                )
+                .submit()
+                .await?
+                .return_value();
+            assert_eq!(sent, Ok(()));
+
+            // Then the runtime refused the re-entrant call before it reached
+            // the token, without failing the outer transfer
+            let callbacks = client
+                .call(&ink_e2e::alice(), &receiver_call.callbacks())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(callbacks, 1);
+            let refused = client
+                .call(&ink_e2e::alice(), &receiver_call.last_reentry_refused())
+                .dry_run()
+                .await?
+                .return_value();
+            assert!(refused);
+            let error = client
+                .call(&ink_e2e::alice(), &receiver_call.last_reentry_error())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(error, None);
+
+            // And the tokens were not sent back
+            let receiver_balance = client
+                .call(&ink_e2e::alice(), &token_call.balance_of(receiver.addr))
+                .dry_run()
+                .await?;
+            assert_eq!(receiver_balance.return_value(), 100);
+            let alice_balance = client
+                .call(&ink_e2e::alice(), &token_call.balance_of(alice))
+                .dry_run()
+                .await?;
+            assert_eq!(alice_balance.return_value(), 900);
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,413 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::reflection_token::ReflectionTokenRef;
+
+#[ink::contract]
+mod reflection_token {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use psp_coin::PSP22Error;
+
+    /// Denominator for the reflection fee in basis points
+    const BPS_DENOMINATOR: u128 = 10_000;
+    /// Upper bound on excluded accounts, which the rate computation iterates
+    const MAX_EXCLUDED: usize = 32;
+
+    /// Storage structure for the reflection token
+    ///
+    /// Holders own reflections, a fixed-size internal unit. The fee taken
+    /// from each transfer is burned from the reflection supply, which
+    /// raises the token value of every remaining reflection and so
+    /// redistributes the fee to all holders without per-holder writes.
+    /// Excluded accounts (pairs, treasuries) hold plain token balances and
+    /// receive no reflections.
+    #[ink(storage)]
+    pub struct ReflectionToken {
+        /// Account allowed to change the fee and the exclusion list
+        owner: Address,
+        /// Share of each transfer redistributed to holders, in basis points
+        fee_bps: u16,
+        /// Total token supply
+        total_supply: u128,
+        /// Total reflections, shrinking as fees are redistributed
+        reflected_total: u128,
+        /// Total fees redistributed so far, in tokens
+        total_fees: u128,
+        /// Mapping from account to reflection balance
+        reflected_balances: Mapping<Address, u128>,
+        /// Mapping from excluded account to token balance
+        token_balances: Mapping<Address, u128>,
+        /// Accounts excluded from reflections
+        excluded: Vec<Address>,
+        /// Nested mapping for allowances (owner, spender) -> amount
+        allowances: Mapping<(Address, Address), u128>,
+    }
+
+    /// Event emitted when tokens are transferred
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        pub from: Option<Address>,
+        #[ink(topic)]
+        pub to: Option<Address>,
+        pub value: u128,
+    }
+
+    /// Event emitted when an approval is granted
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        pub owner: Address,
+        #[ink(topic)]
+        pub spender: Address,
+        pub value: u128,
+    }
+
+    /// Event emitted when a transfer fee is redistributed to holders
+    #[ink(event)]
+    pub struct Reflected {
+        #[ink(topic)]
+        pub from: Address,
+        pub fee: u128,
+    }
+
+    impl ReflectionToken {
+        /// Constructor that mints `total_supply` to the caller
+        #[ink(constructor)]
+        pub fn new(total_supply: u128, fee_bps: u16) -> Result<Self, PSP22Error> {
+            if total_supply == 0 || u128::from(fee_bps) > BPS_DENOMINATOR {
+                return Err(PSP22Error::Custom(String::from("Invalid config")));
+            }
+
+            let caller = Self::env().caller();
+            let reflected_total = u128::MAX - u128::MAX % total_supply;
+
+            let mut reflected_balances = Mapping::default();
+            reflected_balances.insert(caller, &reflected_total);
+
+            Self::env().emit_event(Transfer {
+                from: None,
+                to: Some(caller),
+                value: total_supply,
+            });
+
+            Ok(Self {
+                owner: caller,
+                fee_bps,
+                total_supply,
+                reflected_total,
+                total_fees: 0,
+                reflected_balances,
+                token_balances: Mapping::default(),
+                excluded: Vec::new(),
+                allowances: Mapping::default(),
+            })
+        }
+    }
+
+    impl ReflectionToken {
+        /// Returns the total token supply
+        #[ink(message, selector = 0x162DF8C2)]
+        pub fn total_supply(&self) -> u128 {
+            self.total_supply
+        }
+
+        /// Returns the token balance of `owner`, including reflected fees
+        #[ink(message, selector = 0x6568382F)]
+        pub fn balance_of(&self, owner: Address) -> u128 {
+            if self.is_excluded(owner) {
+                return self.token_balances.get(owner).unwrap_or(0);
+            }
+            self.reflected_balances.get(owner).unwrap_or(0) / self.rate()
+        }
+
+        /// Returns the allowance granted by owner to spender
+        #[ink(message, selector = 0x4D47D921)]
+        pub fn allowance(&self, owner: Address, spender: Address) -> u128 {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        /// Returns the reflection fee in basis points
+        #[ink(message)]
+        pub fn fee_bps(&self) -> u16 {
+            self.fee_bps
+        }
+
+        /// Returns the total fees redistributed so far
+        #[ink(message)]
+        pub fn total_fees(&self) -> u128 {
+            self.total_fees
+        }
+
+        /// Returns whether `account` is excluded from reflections
+        #[ink(message)]
+        pub fn is_excluded(&self, account: Address) -> bool {
+            self.excluded.contains(&account)
+        }
+
+        /// Returns the accounts excluded from reflections
+        #[ink(message)]
+        pub fn excluded_accounts(&self) -> Vec<Address> {
+            self.excluded.clone()
+        }
+
+        /// Returns the tokens `reflected_amount` reflections are currently worth
+        #[ink(message)]
+        pub fn token_from_reflection(&self, reflected_amount: u128) -> Result<u128, PSP22Error> {
+            if reflected_amount > self.reflected_total {
+                return Err(PSP22Error::Custom(String::from("Amount exceeds total reflections")));
+            }
+            Ok(reflected_amount / self.rate())
+        }
+
+        /// Returns the reflections `amount` tokens are currently worth,
+        /// optionally after deducting the transfer fee
+        #[ink(message)]
+        pub fn reflection_from_token(&self, amount: u128, deduct_fee: bool) -> Result<u128, PSP22Error> {
+            if amount > self.total_supply {
+                return Err(PSP22Error::Custom(String::from("Amount exceeds supply")));
+            }
+
+            let amount = if deduct_fee { amount - self.fee_on(amount) } else { amount };
+            Ok(amount * self.rate())
+        }
+
+        /// Transfer tokens from caller to recipient, redistributing the fee
+        #[ink(message, selector = 0xDB20F9F5)]
+        pub fn transfer(&mut self, to: Address, value: u128, _data: Vec<u8>) -> Result<(), PSP22Error> {
+            let from = self.env().caller();
+            self.transfer_tokens(from, to, value)
+        }
+
+        /// Transfer tokens from one account to another using allowance
+        #[ink(message, selector = 0x54B3C76E)]
+        pub fn transfer_from(
+            &mut self,
+            from: Address,
+            to: Address,
+            value: u128,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            let allowance = self.allowance(from, caller);
+            if allowance < value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+
+            self.transfer_tokens(from, to, value)?;
+            self.allowances.insert((from, caller), &(allowance - value));
+            Ok(())
+        }
+
+        /// Approve spender to spend tokens on behalf of caller
+        #[ink(message, selector = 0xB20F1BBD)]
+        pub fn approve(&mut self, spender: Address, value: u128) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), &value);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Change the reflection fee (owner only)
+        #[ink(message)]
+        pub fn set_fee_bps(&mut self, fee_bps: u16) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            if u128::from(fee_bps) > BPS_DENOMINATOR {
+                return Err(PSP22Error::Custom(String::from("Invalid config")));
+            }
+
+            self.fee_bps = fee_bps;
+            Ok(())
+        }
+
+        /// Stop `account` from receiving reflections, freezing its current
+        /// balance as a plain token balance (owner only)
+        #[ink(message)]
+        pub fn exclude_account(&mut self, account: Address) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            if self.is_excluded(account) {
+                return Err(PSP22Error::Custom(String::from("Account already excluded")));
+            }
+            if self.excluded.len() >= MAX_EXCLUDED {
+                return Err(PSP22Error::Custom(String::from("Too many excluded accounts")));
+            }
+
+            let balance = self.balance_of(account);
+            self.token_balances.insert(account, &balance);
+            self.excluded.push(account);
+            Ok(())
+        }
+
+        /// Let `account` receive reflections again (owner only)
+        #[ink(message)]
+        pub fn include_account(&mut self, account: Address) -> Result<(), PSP22Error> {
+            self.ensure_owner()?;
+            let index = self
+                .excluded
+                .iter()
+                .position(|excluded| *excluded == account)
+                .ok_or(PSP22Error::Custom(String::from("Account not excluded")))?;
+
+            // Re-derive reflections from the frozen balance at the holders'
+            // rate, so including the account leaves that rate unchanged
+            let rate = self.rate();
+            let balance = self.token_balances.take(account).unwrap_or(0);
+            let stale = self.reflected_balances.get(account).unwrap_or(0);
+            let reflected = balance * rate;
+            self.reflected_total = (self.reflected_total - stale)
+                .checked_add(reflected)
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+            self.reflected_balances.insert(account, &reflected);
+            self.excluded.swap_remove(index);
+            Ok(())
+        }
+    }
+
+    impl ReflectionToken {
+        /// Fails unless the caller is the owner
+        fn ensure_owner(&self) -> Result<(), PSP22Error> {
+            if self.env().caller() != self.owner {
+                return Err(PSP22Error::Unauthorized);
+            }
+            Ok(())
+        }
+
+        /// Fee taken from a transfer of `amount` tokens
+        fn fee_on(&self, amount: u128) -> u128 {
+            amount.saturating_mul(u128::from(self.fee_bps)) / BPS_DENOMINATOR
+        }
+
+        /// Reflections per token among accounts that receive reflections
+        fn rate(&self) -> u128 {
+            let default_rate = self.reflected_total / self.total_supply;
+            let mut reflected_supply = self.reflected_total;
+            let mut token_supply = self.total_supply;
+
+            for account in &self.excluded {
+                let reflected = self.reflected_balances.get(account).unwrap_or(0);
+                let tokens = self.token_balances.get(account).unwrap_or(0);
+                if reflected > reflected_supply || tokens > token_supply {
+                    return default_rate;
+                }
+                reflected_supply -= reflected;
+                token_supply -= tokens;
+            }
+
+            // Fall back while nearly all tokens sit in excluded accounts
+            if token_supply == 0 || reflected_supply < default_rate {
+                return default_rate;
+            }
+            reflected_supply / token_supply
+        }
+
+        /// Move `value` tokens, redistributing the fee to all holders
+        fn transfer_tokens(&mut self, from: Address, to: Address, value: u128) -> Result<(), PSP22Error> {
+            if value > self.balance_of(from) {
+                return Err(PSP22Error::InsufficientBalance);
+            }
+
+            let rate = self.rate();
+            let fee = self.fee_on(value);
+            let reflected_value = value * rate;
+            let reflected_fee = fee * rate;
+
+            let from_reflected = self.reflected_balances.get(from).unwrap_or(0);
+            self.reflected_balances
+                .insert(from, &from_reflected.saturating_sub(reflected_value));
+            if self.is_excluded(from) {
+                let from_tokens = self.token_balances.get(from).unwrap_or(0);
+                self.token_balances.insert(from, &(from_tokens - value));
+            }
+
+            let to_reflected = self.reflected_balances.get(to).unwrap_or(0);
+            self.reflected_balances
+                .insert(to, &(to_reflected + reflected_value - reflected_fee));
+            if self.is_excluded(to) {
+                let to_tokens = self.token_balances.get(to).unwrap_or(0);
+                self.token_balances.insert(to, &(to_tokens + value - fee));
+            }
+
+            self.reflected_total -= reflected_fee;
+            self.total_fees += fee;
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: value - fee,
+            });
+            if fee > 0 {
+                self.env().emit_event(Reflected { from, fee });
+            }
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn transfer_fee_is_redistributed_to_holders() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut token = ReflectionToken::new(1_000, 2_500).unwrap();
+            assert_eq!(token.transfer(accounts.bob, 800, Vec::new()), Ok(()));
+
+            // Bob receives 600 and the 200 fee is shared 1:3 by alice and bob
+            assert_eq!(token.balance_of(accounts.alice), 250);
+            assert_eq!(token.balance_of(accounts.bob), 750);
+            assert_eq!(token.total_fees(), 200);
+            assert_eq!(token.total_supply(), 1_000);
+            assert_eq!(
+                token.transfer(accounts.bob, 251, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn excluded_accounts_receive_no_reflections() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut token = ReflectionToken::new(1_000, 0).unwrap();
+            assert_eq!(token.exclude_account(accounts.charlie), Ok(()));
+            assert_eq!(token.transfer(accounts.charlie, 500, Vec::new()), Ok(()));
+
+            assert_eq!(token.set_fee_bps(2_500), Ok(()));
+            assert_eq!(token.transfer(accounts.bob, 400, Vec::new()), Ok(()));
+
+            assert_eq!(token.balance_of(accounts.charlie), 500);
+            assert_eq!(token.balance_of(accounts.alice), 125);
+            assert_eq!(token.balance_of(accounts.bob), 375);
+
+            assert_eq!(token.include_account(accounts.charlie), Ok(()));
+            assert!(!token.is_excluded(accounts.charlie));
+            assert_eq!(token.balance_of(accounts.charlie), 500);
+        }
+
+        #[ink::test]
+        fn reflection_conversions_round_trip() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut token = ReflectionToken::new(1_000, 1_000).unwrap();
+            let reflected = token.reflection_from_token(100, false).unwrap();
+            assert_eq!(token.token_from_reflection(reflected), Ok(100));
+
+            let reflected = token.reflection_from_token(100, true).unwrap();
+            assert_eq!(token.token_from_reflection(reflected), Ok(90));
+            assert_eq!(
+                token.reflection_from_token(1_001, false),
+                Err(PSP22Error::Custom(String::from("Amount exceeds supply")))
+            );
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(token.exclude_account(accounts.bob), Err(PSP22Error::Unauthorized));
+        }
+    }
+}
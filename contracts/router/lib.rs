@@ -0,0 +1,250 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::router::RouterRef;
+
+#[ink::contract]
+mod router {
+    use ink::prelude::{string::String, vec::Vec};
+    use ink::env::{
+        call::{build_call, ExecutionInput, FromAddr, Selector},
+        DefaultEnvironment,
+    };
+    use ink::U256;
+    use pair::PairRef;
+    use psp_coin::{PSP22Error, PspCoinRef};
+
+    /// Selector of the pair's `swap(zero_for_one, amount_in, min_amount_out, to)`
+    const SWAP_SELECTOR: [u8; 4] = [0x11, 0x00, 0x4f, 0xa6];
+
+    /// Storage structure for the swap router
+    ///
+    /// Stateless: a swap path is a list of (pair, zero_for_one) hops, and
+    /// intermediate outputs are held by the router only within a call.
+    #[ink(storage)]
+    #[derive(Default)]
+    pub struct Router {}
+
+    impl Router {
+        /// Constructor that creates the router
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {}
+        }
+    }
+
+    impl Router {
+        /// Returns the output of every hop when swapping `amount_in` along `path`
+        #[ink(message)]
+        pub fn get_amounts_out(&self, amount_in: u128, path: Vec<(Address, bool)>) -> Vec<u128> {
+            let mut amounts = Vec::with_capacity(path.len());
+            let mut amount = amount_in;
+
+            for (pair, zero_for_one) in path {
+                let pair: PairRef = FromAddr::from_addr(pair);
+                amount = pair.get_amount_out(amount, zero_for_one);
+                amounts.push(amount);
+            }
+
+            amounts
+        }
+
+        /// Swap exactly `amount_in` along `path`, sending at least
+        /// `min_amount_out` of the last output to `to`
+        ///
+        /// A token input is pulled from the caller, who must have approved
+        /// the router; a native input must be sent with the call.
+        #[ink(message, payable)]
+        pub fn swap_exact_in(
+            &mut self,
+            amount_in: u128,
+            min_amount_out: u128,
+            path: Vec<(Address, bool)>,
+            to: Address,
+        ) -> Result<u128, PSP22Error> {
+            if path.is_empty() {
+                return Err(PSP22Error::Custom(String::from("Empty path")));
+            }
+
+            let caller = self.env().caller();
+            let this = self.env().address();
+            let mut amount = amount_in;
+
+            for (index, &(pair_address, zero_for_one)) in path.iter().enumerate() {
+                let pair: PairRef = FromAddr::from_addr(pair_address);
+                let (token0, token1) = pair.tokens();
+                let token_in = if zero_for_one { Some(token0) } else { token1 };
+                let first = index == 0;
+
+                let value = match token_in {
+                    Some(token) => {
+                        if first && !self.env().transferred_value().is_zero() {
+                            return Err(PSP22Error::Custom(String::from("Value mismatch")));
+                        }
+
+                        let mut token: PspCoinRef = FromAddr::from_addr(token);
+                        if first {
+                            token.transfer_from(caller, this, amount, Vec::new())?;
+                        }
+                        token.approve(pair_address, amount)?;
+                        U256::zero()
+                    }
+                    None => {
+                        if first && self.env().transferred_value() != U256::from(amount_in) {
+                            return Err(PSP22Error::Custom(String::from("Value mismatch")));
+                        }
+                        U256::from(amount)
+                    }
+                };
+
+                let recipient = if index + 1 == path.len() { to } else { this };
+                amount = Self::swap_on(pair_address, value, zero_for_one, amount, recipient)?;
+            }
+
+            if amount < min_amount_out {
+                return Err(PSP22Error::Custom(String::from("Slippage exceeded")));
+            }
+
+            Ok(amount)
+        }
+    }
+
+    impl Router {
+        /// Run a single swap on `pair`, forwarding `value` as native input
+        fn swap_on(
+            pair: Address,
+            value: U256,
+            zero_for_one: bool,
+            amount_in: u128,
+            to: Address,
+        ) -> Result<u128, PSP22Error> {
+            let result = build_call::<DefaultEnvironment>()
+                .call(pair)
+                .transferred_value(value)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(SWAP_SELECTOR))
+                        .push_arg(zero_for_one)
+                        .push_arg(amount_in)
+                        .push_arg(0u128)
+                        .push_arg(to),
+                )
+                .returns::<Result<u128, PSP22Error>>()
+                .try_invoke();
+
+            match result {
+                Ok(Ok(Ok(amount_out))) => Ok(amount_out),
+                Ok(Ok(Err(error))) => Err(error),
+                _ => Err(PSP22Error::Custom(String::from("Swap call failed"))),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn swap_fails_for_empty_path() {
+            let accounts = ink::env::test::default_accounts();
+            let mut router = Router::new();
+
+            assert_eq!(
+                router.swap_exact_in(100, 0, vec![], accounts.alice),
+                Err(PSP22Error::Custom(String::from("Empty path")))
+            );
+            assert_eq!(router.get_amounts_out(100, vec![]), Vec::<u128>::new());
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use pair::Pair;
+        use psp_coin::PspCoin;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn swaps_across_two_pairs<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given two tokens, each paired with the native currency
+            let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+            let mut pairs = Vec::new();
+            let mut tokens = Vec::new();
+            for _ in 0..2 {
+                let mut token_constructor = PspCoinRef::new_with_supply(1_000_000);
+                let token = client
+                    .instantiate("psp_coin", &ink_e2e::alice(), &mut token_constructor)
+                    .submit()
+                    .await
+                    .expect("token instantiate failed");
+                let mut token_call = token.call_builder::<PspCoin>();
+
+                let mut pair_constructor = PairRef::new(token.addr, None);
+                let pair = client
+                    .instantiate("pair", &ink_e2e::alice(), &mut pair_constructor)
+                    .submit()
+                    .await
+                    .expect("pair instantiate failed");
+                let mut pair_call = pair.call_builder::<Pair>();
+
+                client
+                    .call(&ink_e2e::alice(), &token_call.approve(pair.addr, 10_000))
+                    .submit()
+                    .await?;
+                client
+                    .call(&ink_e2e::alice(), &pair_call.add_liquidity(10_000, 10_000, 0))
+                    .value(10_000)
+                    .submit()
+                    .await?;
+
+                pairs.push(pair.addr);
+                tokens.push(token);
+            }
+
+            let mut router_constructor = RouterRef::new();
+            let router = client
+                .instantiate("router", &ink_e2e::alice(), &mut router_constructor)
+                .submit()
+                .await
+                .expect("router instantiate failed");
+            let mut router_call = router.call_builder::<Router>();
+
+            let mut token_a = tokens[0].call_builder::<PspCoin>();
+            let mut token_b = tokens[1].call_builder::<PspCoin>();
+            client
+                .call(&ink_e2e::alice(), &token_a.transfer(bob, 1_000, Vec::new()))
+                .submit()
+                .await?;
+            client
+                .call(&ink_e2e::bob(), &token_a.approve(router.addr, 1_000))
+                .submit()
+                .await?;
+
+            // When bob swaps token A for token B through the native currency
+            let path = vec![(pairs[0], true), (pairs[1], false)];
+            let quoted = client
+                .call(&ink_e2e::bob(), &router_call.get_amounts_out(1_000, path.clone()))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(quoted, vec![906, 828]);
+
+            client
+                .call(&ink_e2e::bob(), &router_call.swap_exact_in(1_000, 828, path, bob))
+                .submit()
+                .await?;
+
+            // Then bob received the quoted amount of token B
+            let balance = client
+                .call(&ink_e2e::bob(), &token_b.balance_of(bob))
+                .dry_run()
+                .await?;
+            assert_eq!(balance.return_value(), 828);
+
+            Ok(())
+        }
+    }
+}
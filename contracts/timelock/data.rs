@@ -0,0 +1,37 @@
+use ink::prelude::vec::Vec;
+use ink::Address;
+
+/// Timelock error types
+#[derive(Debug, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum TimelockError {
+    /// Caller lacks the role required for this action
+    Unauthorized,
+    /// Requested delay is shorter than the minimum delay
+    DelayTooShort,
+    /// An operation with the same id is already scheduled
+    AlreadyScheduled,
+    /// No pending operation exists with the given id
+    OperationNotFound,
+    /// The operation's delay has not elapsed yet
+    NotReady,
+    /// The scheduled call failed
+    ExecutionFailed,
+    /// Arithmetic overflow
+    Overflow,
+}
+
+/// A call waiting in the timelock
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Operation {
+    /// Contract to call
+    pub callee: Address,
+    /// Selector of the message to call
+    pub selector: [u8; 4],
+    /// SCALE encoded message arguments
+    pub input: Vec<u8>,
+    /// Time from which the call can be executed
+    pub ready_at: u64,
+}
@@ -0,0 +1,432 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+mod data;
+
+pub use data::{Operation, TimelockError};
+pub use self::timelock::TimelockRef;
+
+#[ink::contract]
+mod timelock {
+    use ink::{storage::Mapping, prelude::vec::Vec};
+    use ink::env::{
+        call::{build_call, ExecutionInput, Selector},
+        hash::Blake2x256,
+        DefaultEnvironment,
+    };
+    use ink::scale::{Decode, Encode, Input, Output};
+
+    use crate::data::{Operation, TimelockError};
+
+    /// Selector of `update_delay(min_delay)`
+    const UPDATE_DELAY_SELECTOR: [u8; 4] = [0x43, 0xe4, 0x92, 0xf5];
+
+    /// Selector of `set_proposer(account, enabled)`
+    const SET_PROPOSER_SELECTOR: [u8; 4] = [0x68, 0x9f, 0xba, 0x77];
+
+    /// Selector of `set_executor(account, enabled)`
+    const SET_EXECUTOR_SELECTOR: [u8; 4] = [0x22, 0x78, 0xb3, 0x30];
+
+    /// Raw, already SCALE encoded call arguments
+    struct CallInput<'a>(&'a [u8]);
+
+    impl Encode for CallInput<'_> {
+        fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(self.0);
+        }
+    }
+
+    /// Return data of an executed call, consumed without being decoded
+    struct CallOutput;
+
+    impl Decode for CallOutput {
+        fn decode<I: Input>(input: &mut I) -> Result<Self, ink::scale::Error> {
+            while input.read_byte().is_ok() {}
+            Ok(CallOutput)
+        }
+    }
+
+    /// Storage structure for the timelock
+    #[ink(storage)]
+    pub struct Timelock {
+        /// Minimum time between scheduling and executing a call
+        min_delay: Timestamp,
+        /// Accounts allowed to schedule and cancel calls
+        proposers: Mapping<Address, ()>,
+        /// Accounts allowed to execute ready calls
+        executors: Mapping<Address, ()>,
+        /// Pending operations by id
+        operations: Mapping<[u8; 32], Operation>,
+    }
+
+    /// Event emitted when a call is scheduled
+    #[ink(event)]
+    pub struct CallScheduled {
+        #[ink(topic)]
+        pub id: [u8; 32],
+        #[ink(topic)]
+        pub callee: Address,
+        pub selector: [u8; 4],
+        pub input: Vec<u8>,
+        pub ready_at: Timestamp,
+    }
+
+    /// Event emitted when a scheduled call is executed
+    #[ink(event)]
+    pub struct CallExecuted {
+        #[ink(topic)]
+        pub id: [u8; 32],
+    }
+
+    /// Event emitted when a scheduled call is cancelled
+    #[ink(event)]
+    pub struct Cancelled {
+        #[ink(topic)]
+        pub id: [u8; 32],
+    }
+
+    impl Timelock {
+        /// Constructor that sets the minimum delay and initial roles
+        ///
+        /// Afterwards roles and the delay can only be changed by the timelock
+        /// itself, i.e. through a scheduled call.
+        #[ink(constructor)]
+        pub fn new(min_delay: Timestamp, proposers: Vec<Address>, executors: Vec<Address>) -> Self {
+            let mut instance = Self {
+                min_delay,
+                proposers: Mapping::default(),
+                executors: Mapping::default(),
+                operations: Mapping::default(),
+            };
+
+            for proposer in proposers {
+                instance.proposers.insert(proposer, &());
+            }
+            for executor in executors {
+                instance.executors.insert(executor, &());
+            }
+
+            instance
+        }
+    }
+
+    impl Timelock {
+        /// Returns the minimum delay
+        #[ink(message)]
+        pub fn min_delay(&self) -> Timestamp {
+            self.min_delay
+        }
+
+        /// Returns whether `account` may schedule and cancel calls
+        #[ink(message)]
+        pub fn is_proposer(&self, account: Address) -> bool {
+            self.proposers.contains(account)
+        }
+
+        /// Returns whether `account` may execute ready calls
+        #[ink(message)]
+        pub fn is_executor(&self, account: Address) -> bool {
+            self.executors.contains(account)
+        }
+
+        /// Returns the pending operation with the given id
+        #[ink(message)]
+        pub fn operation(&self, id: [u8; 32]) -> Option<Operation> {
+            self.operations.get(id)
+        }
+
+        /// Returns whether the operation exists and its delay has elapsed
+        #[ink(message)]
+        pub fn is_operation_ready(&self, id: [u8; 32]) -> bool {
+            self.operations
+                .get(id)
+                .is_some_and(|operation| self.env().block_timestamp() >= operation.ready_at)
+        }
+
+        /// Returns the id of an operation
+        #[ink(message)]
+        pub fn hash_operation(
+            &self,
+            callee: Address,
+            selector: [u8; 4],
+            input: Vec<u8>,
+            salt: [u8; 32],
+        ) -> [u8; 32] {
+            self.env()
+                .hash_encoded::<Blake2x256, _>(&(callee, selector, input, salt))
+        }
+
+        /// Schedule a call executable after `delay` (proposers only)
+        #[ink(message)]
+        pub fn schedule(
+            &mut self,
+            callee: Address,
+            selector: [u8; 4],
+            input: Vec<u8>,
+            salt: [u8; 32],
+            delay: Timestamp,
+        ) -> Result<[u8; 32], TimelockError> {
+            self.ensure_proposer()?;
+
+            if delay < self.min_delay {
+                return Err(TimelockError::DelayTooShort);
+            }
+
+            let id = self.hash_operation(callee, selector, input.clone(), salt);
+            if self.operations.contains(id) {
+                return Err(TimelockError::AlreadyScheduled);
+            }
+
+            let ready_at = self
+                .env()
+                .block_timestamp()
+                .checked_add(delay)
+                .ok_or(TimelockError::Overflow)?;
+
+            self.operations.insert(
+                id,
+                &Operation {
+                    callee,
+                    selector,
+                    input: input.clone(),
+                    ready_at,
+                },
+            );
+
+            self.env().emit_event(CallScheduled {
+                id,
+                callee,
+                selector,
+                input,
+                ready_at,
+            });
+
+            Ok(id)
+        }
+
+        /// Cancel a pending operation (proposers only)
+        #[ink(message)]
+        pub fn cancel(&mut self, id: [u8; 32]) -> Result<(), TimelockError> {
+            self.ensure_proposer()?;
+
+            if self.operations.take(id).is_none() {
+                return Err(TimelockError::OperationNotFound);
+            }
+
+            self.env().emit_event(Cancelled { id });
+
+            Ok(())
+        }
+
+        /// Execute a ready operation (executors only)
+        #[ink(message)]
+        pub fn execute(&mut self, id: [u8; 32]) -> Result<(), TimelockError> {
+            if !self.executors.contains(self.env().caller()) {
+                return Err(TimelockError::Unauthorized);
+            }
+
+            let operation = self
+                .operations
+                .get(id)
+                .ok_or(TimelockError::OperationNotFound)?;
+            if self.env().block_timestamp() < operation.ready_at {
+                return Err(TimelockError::NotReady);
+            }
+
+            // Remove before the call so it cannot be executed twice
+            self.operations.remove(id);
+
+            if operation.callee == self.env().address() {
+                self.dispatch_self(operation.selector, &operation.input)?;
+            } else {
+                build_call::<DefaultEnvironment>()
+                    .call(operation.callee)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(operation.selector))
+                            .push_arg(CallInput(&operation.input)),
+                    )
+                    .returns::<CallOutput>()
+                    .try_invoke()
+                    .map_err(|_| TimelockError::ExecutionFailed)?
+                    .map_err(|_| TimelockError::ExecutionFailed)?;
+            }
+
+            self.env().emit_event(CallExecuted { id });
+
+            Ok(())
+        }
+
+        /// Change the minimum delay (timelock only)
+        ///
+        /// Schedule it with the timelock as callee; `execute` applies such
+        /// operations directly since the runtime refuses self-calls.
+        #[ink(message)]
+        pub fn update_delay(&mut self, min_delay: Timestamp) -> Result<(), TimelockError> {
+            self.ensure_self()?;
+            self.min_delay = min_delay;
+            Ok(())
+        }
+
+        /// Grant or revoke the proposer role (timelock only)
+        #[ink(message)]
+        pub fn set_proposer(&mut self, account: Address, enabled: bool) -> Result<(), TimelockError> {
+            self.ensure_self()?;
+            Self::set_role(&mut self.proposers, account, enabled);
+            Ok(())
+        }
+
+        /// Grant or revoke the executor role (timelock only)
+        #[ink(message)]
+        pub fn set_executor(&mut self, account: Address, enabled: bool) -> Result<(), TimelockError> {
+            self.ensure_self()?;
+            Self::set_role(&mut self.executors, account, enabled);
+            Ok(())
+        }
+    }
+
+    impl Timelock {
+        /// Fails unless the caller holds the proposer role
+        fn ensure_proposer(&self) -> Result<(), TimelockError> {
+            if !self.proposers.contains(self.env().caller()) {
+                return Err(TimelockError::Unauthorized);
+            }
+            Ok(())
+        }
+
+        /// Fails unless the timelock is calling itself
+        fn ensure_self(&self) -> Result<(), TimelockError> {
+            if self.env().caller() != self.env().address() {
+                return Err(TimelockError::Unauthorized);
+            }
+            Ok(())
+        }
+
+        /// Apply an operation scheduled on the timelock itself
+        ///
+        /// Calling back into the executing contract is refused by the
+        /// runtime, so only the admin messages are supported and they are
+        /// decoded and applied in place.
+        fn dispatch_self(&mut self, selector: [u8; 4], mut input: &[u8]) -> Result<(), TimelockError> {
+            match selector {
+                UPDATE_DELAY_SELECTOR => {
+                    self.min_delay = Timestamp::decode(&mut input)
+                        .map_err(|_| TimelockError::ExecutionFailed)?;
+                }
+                SET_PROPOSER_SELECTOR => {
+                    let (account, enabled) = <(Address, bool)>::decode(&mut input)
+                        .map_err(|_| TimelockError::ExecutionFailed)?;
+                    Self::set_role(&mut self.proposers, account, enabled);
+                }
+                SET_EXECUTOR_SELECTOR => {
+                    let (account, enabled) = <(Address, bool)>::decode(&mut input)
+                        .map_err(|_| TimelockError::ExecutionFailed)?;
+                    Self::set_role(&mut self.executors, account, enabled);
+                }
+                _ => return Err(TimelockError::ExecutionFailed),
+            }
+            Ok(())
+        }
+
+        /// Grant or revoke a role
+        fn set_role(role: &mut Mapping<Address, ()>, account: Address, enabled: bool) {
+            if enabled {
+                role.insert(account, &());
+            } else {
+                role.remove(account);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn schedule_works() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut timelock = Timelock::new(100, vec![accounts.alice], vec![accounts.bob]);
+
+            let id = timelock
+                .schedule(accounts.django, [1, 2, 3, 4], vec![], [0; 32], 100)
+                .expect("schedule works");
+            assert_eq!(timelock.operation(id).map(|op| op.ready_at), Some(100));
+            assert!(!timelock.is_operation_ready(id));
+
+            // Same call and salt cannot be scheduled twice
+            assert_eq!(
+                timelock.schedule(accounts.django, [1, 2, 3, 4], vec![], [0; 32], 100),
+                Err(TimelockError::AlreadyScheduled)
+            );
+        }
+
+        #[ink::test]
+        fn schedule_enforces_roles_and_delay() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut timelock = Timelock::new(100, vec![accounts.alice], vec![accounts.bob]);
+
+            assert_eq!(
+                timelock.schedule(accounts.django, [0; 4], vec![], [0; 32], 99),
+                Err(TimelockError::DelayTooShort)
+            );
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(
+                timelock.schedule(accounts.django, [0; 4], vec![], [0; 32], 100),
+                Err(TimelockError::Unauthorized)
+            );
+            assert_eq!(timelock.update_delay(0), Err(TimelockError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn execute_fails_before_ready() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut timelock = Timelock::new(100, vec![accounts.alice], vec![accounts.bob]);
+            let id = timelock
+                .schedule(accounts.django, [0; 4], vec![], [0; 32], 100)
+                .expect("schedule works");
+
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(timelock.execute(id), Err(TimelockError::NotReady));
+
+            ink::env::test::set_caller(accounts.alice);
+            assert_eq!(timelock.cancel(id), Ok(()));
+            assert_eq!(timelock.operation(id), None);
+        }
+
+        #[ink::test]
+        fn scheduled_self_calls_update_settings() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut timelock = Timelock::new(100, vec![accounts.alice], vec![accounts.bob]);
+            let this = ink::env::test::callee::<DefaultEnvironment>();
+
+            let delay_id = timelock
+                .schedule(this, UPDATE_DELAY_SELECTOR, 50u64.encode(), [0; 32], 100)
+                .expect("schedule works");
+            let proposer_id = timelock
+                .schedule(
+                    this,
+                    SET_PROPOSER_SELECTOR,
+                    (accounts.charlie, true).encode(),
+                    [0; 32],
+                    100,
+                )
+                .expect("schedule works");
+
+            ink::env::test::set_block_timestamp::<DefaultEnvironment>(100);
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(timelock.execute(delay_id), Ok(()));
+            assert_eq!(timelock.execute(proposer_id), Ok(()));
+
+            assert_eq!(timelock.min_delay(), 50);
+            assert!(timelock.is_proposer(accounts.charlie));
+            assert_eq!(timelock.operation(delay_id), None);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::token_factory::TokenFactoryRef;
+
+#[ink::contract]
+mod token_factory {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use ink::env::hash::Blake2x256;
+    use ink::ToAddr;
+    use psp_coin::{PSP22Error, PspCoinRef, TokenConfig};
+
+    /// Storage structure for the token factory
+    ///
+    /// Deploys `PspCoin` instances from an uploaded code hash. The salt is
+    /// mixed with the creator's address, so each creator controls its own
+    /// deterministic address space and cannot be front-run into another's.
+    #[ink(storage)]
+    pub struct TokenFactory {
+        /// Code hash of the uploaded `PspCoin` contract
+        token_code_hash: Hash,
+        /// Deployed tokens in creation order
+        tokens: Mapping<u32, Address>,
+        /// Number of tokens deployed so far
+        token_count: u32,
+        /// Tokens deployed by each creator
+        tokens_by_creator: Mapping<Address, Vec<Address>>,
+        /// Creator of each deployed token
+        creators: Mapping<Address, Address>,
+    }
+
+    /// Event emitted when a token is deployed
+    #[ink(event)]
+    pub struct TokenCreated {
+        #[ink(topic)]
+        pub token: Address,
+        #[ink(topic)]
+        pub creator: Address,
+        pub name: String,
+        pub symbol: String,
+    }
+
+    impl TokenFactory {
+        /// Constructor that deploys tokens from `token_code_hash`
+        #[ink(constructor)]
+        pub fn new(token_code_hash: Hash) -> Self {
+            Self {
+                token_code_hash,
+                tokens: Mapping::default(),
+                token_count: 0,
+                tokens_by_creator: Mapping::default(),
+                creators: Mapping::default(),
+            }
+        }
+    }
+
+    impl TokenFactory {
+        /// Returns the code hash new tokens are deployed from
+        #[ink(message)]
+        pub fn token_code_hash(&self) -> Hash {
+            self.token_code_hash
+        }
+
+        /// Returns the number of tokens deployed so far
+        #[ink(message)]
+        pub fn token_count(&self) -> u32 {
+            self.token_count
+        }
+
+        /// Returns the token deployed at position `index`
+        #[ink(message)]
+        pub fn token_at(&self, index: u32) -> Option<Address> {
+            self.tokens.get(index)
+        }
+
+        /// Returns the tokens deployed by `creator`
+        #[ink(message)]
+        pub fn tokens_of(&self, creator: Address) -> Vec<Address> {
+            self.tokens_by_creator.get(creator).unwrap_or_default()
+        }
+
+        /// Returns the creator of `token` if this factory deployed it
+        #[ink(message)]
+        pub fn creator_of(&self, token: Address) -> Option<Address> {
+            self.creators.get(token)
+        }
+
+        /// Deploy a token minting `initial_supply` to the caller, who becomes its owner
+        ///
+        /// The same caller and salt always yield the same address, so a
+        /// salt cannot be reused.
+        #[ink(message)]
+        pub fn create_token(
+            &mut self,
+            name: String,
+            symbol: String,
+            decimals: u8,
+            initial_supply: u128,
+            salt: [u8; 32],
+        ) -> Result<Address, PSP22Error> {
+            let creator = self.env().caller();
+
+            let mut creator_salt = [0u8; 32];
+            ink::env::hash_encoded::<Blake2x256, _>(&(creator, salt), &mut creator_salt);
+
+            let token = PspCoinRef::new_with_config(TokenConfig {
+                name: name.clone(),
+                symbol: symbol.clone(),
+                decimals,
+                initial_supply,
+                cap: None,
+                owner: creator,
+                fee_bps: 0,
+                treasury: None,
+                paused: false,
+            })
+            .code_hash(self.token_code_hash)
+            .endowment(0.into())
+            .salt_bytes(Some(creator_salt))
+            .try_instantiate();
+
+            let token = match token {
+                Ok(Ok(Ok(token))) => token.to_addr(),
+                _ => return Err(PSP22Error::Custom(String::from("Instantiation failed"))),
+            };
+
+            let index = self.token_count;
+            self.token_count = index
+                .checked_add(1)
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+            self.tokens.insert(index, &token);
+            self.creators.insert(token, &creator);
+
+            let mut created = self.tokens_of(creator);
+            created.push(token);
+            self.tokens_by_creator.insert(creator, &created);
+
+            self.env().emit_event(TokenCreated {
+                token,
+                creator,
+                name,
+                symbol,
+            });
+
+            Ok(token)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn new_factory_has_empty_registry() {
+            let accounts = ink::env::test::default_accounts();
+            let factory = TokenFactory::new(Hash::from([7; 32]));
+
+            assert_eq!(factory.token_code_hash(), Hash::from([7; 32]));
+            assert_eq!(factory.token_count(), 0);
+            assert_eq!(factory.token_at(0), None);
+            assert_eq!(factory.tokens_of(accounts.alice), Vec::<Address>::new());
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use psp_coin::PspCoin;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn creates_registered_tokens<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // Given a factory for the uploaded token code
+            let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+            let token_code_hash = client
+                .upload("psp_coin", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("token upload failed")
+                .code_hash;
+
+            let mut factory_constructor = TokenFactoryRef::new(token_code_hash);
+            let factory = client
+                .instantiate("token_factory", &ink_e2e::alice(), &mut factory_constructor)
+                .submit()
+                .await
+                .expect("factory instantiate failed");
+            let mut factory_call = factory.call_builder::<TokenFactory>();
+
+            // When bob creates a token
+            let create = factory_call.create_token(
+                String::from("Launch"),
+                String::from("LNCH"),
+                12,
+                5_000,
+                [1; 32],
+            );
+            let token = client
+                .call(&ink_e2e::bob(), &create)
+                .submit()
+                .await?
+                .return_value()
+                .expect("token creation failed");
+
+            // Then it is registered and bob holds the supply
+            let tokens = client
+                .call(&ink_e2e::bob(), &factory_call.tokens_of(bob))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(tokens, vec![token]);
+
+            let token_call = ink_e2e::create_call_builder::<PspCoin>(token);
+            let balance = client
+                .call(&ink_e2e::bob(), &token_call.balance_of(bob))
+                .dry_run()
+                .await?;
+            assert_eq!(balance.return_value(), 5_000);
+
+            // And the same salt cannot deploy twice
+            let again = client
+                .call(&ink_e2e::bob(), &create)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(again, Err(PSP22Error::Custom(String::from("Instantiation failed"))));
+
+            Ok(())
+        }
+    }
+}
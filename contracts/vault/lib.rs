@@ -0,0 +1,516 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::vault::VaultRef;
+
+/// Computes `a * b / denominator` in 256-bit precision, rounding up if asked
+pub fn mul_div(a: u128, b: u128, denominator: u128, round_up: bool) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+
+    let product = ink::U256::from(a) * ink::U256::from(b);
+    let denominator = ink::U256::from(denominator);
+    let mut quotient = product / denominator;
+    if round_up && !(product % denominator).is_zero() {
+        quotient += ink::U256::one();
+    }
+
+    (quotient <= ink::U256::from(u128::MAX)).then(|| quotient.low_u128())
+}
+
+#[ink::contract]
+mod vault {
+    use ink::{storage::Mapping, prelude::vec::Vec, prelude::string::String};
+    use ink::env::call::FromAddr;
+    use psp_coin::{PSP22Error, PspCoinRef};
+
+    use crate::mul_div;
+
+    /// Storage structure for the tokenized vault
+    ///
+    /// Vault shares are themselves a PSP22 token backed by deposits of the
+    /// underlying `asset`.
+    #[ink(storage)]
+    pub struct Vault {
+        /// Underlying PSP22 asset held by the vault
+        asset: Address,
+        /// Total supply of vault shares
+        total_supply: u128,
+        /// Mapping from account to share balance
+        balances: Mapping<Address, u128>,
+        /// Nested mapping for share allowances (owner, spender) -> amount
+        allowances: Mapping<(Address, Address), u128>,
+        /// Share token metadata (name, symbol, decimals)
+        metadata: (String, String, u8),
+    }
+
+    /// Event emitted when shares are transferred
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        pub from: Option<Address>,
+        #[ink(topic)]
+        pub to: Option<Address>,
+        pub value: u128,
+    }
+
+    /// Event emitted when a share approval is granted
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        pub owner: Address,
+        #[ink(topic)]
+        pub spender: Address,
+        pub value: u128,
+    }
+
+    /// Event emitted when assets are deposited for shares
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        pub sender: Address,
+        #[ink(topic)]
+        pub owner: Address,
+        pub assets: u128,
+        pub shares: u128,
+    }
+
+    /// Event emitted when shares are redeemed for assets
+    #[ink(event)]
+    pub struct Withdraw {
+        #[ink(topic)]
+        pub sender: Address,
+        #[ink(topic)]
+        pub receiver: Address,
+        #[ink(topic)]
+        pub owner: Address,
+        pub assets: u128,
+        pub shares: u128,
+    }
+
+    impl Vault {
+        /// Constructor that creates a vault over `asset`
+        #[ink(constructor)]
+        pub fn new(asset: Address, name: String, symbol: String, decimals: u8) -> Self {
+            Self {
+                asset,
+                total_supply: 0,
+                balances: Mapping::default(),
+                allowances: Mapping::default(),
+                metadata: (name, symbol, decimals),
+            }
+        }
+    }
+
+    impl Vault {
+        /// Returns the total share supply
+        #[ink(message)]
+        pub fn total_supply(&self) -> u128 {
+            self.total_supply
+        }
+
+        /// Returns the share balance of the specified owner
+        #[ink(message)]
+        pub fn balance_of(&self, owner: Address) -> u128 {
+            self.balances.get(owner).unwrap_or(0)
+        }
+
+        /// Returns the share allowance granted by owner to spender
+        #[ink(message)]
+        pub fn allowance(&self, owner: Address, spender: Address) -> u128 {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        /// Transfer shares from caller to recipient
+        #[ink(message)]
+        pub fn transfer(&mut self, to: Address, value: u128, _data: Vec<u8>) -> Result<(), PSP22Error> {
+            let from = self.env().caller();
+            self.transfer_shares(from, to, value)
+        }
+
+        /// Transfer shares from one account to another using allowance
+        #[ink(message)]
+        pub fn transfer_from(
+            &mut self,
+            from: Address,
+            to: Address,
+            value: u128,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            self.spend_allowance(from, caller, value)?;
+            self.transfer_shares(from, to, value)
+        }
+
+        /// Approve spender to spend shares on behalf of caller
+        #[ink(message)]
+        pub fn approve(&mut self, spender: Address, value: u128) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.allowances.insert((owner, spender), &value);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Returns the share token name
+        #[ink(message)]
+        pub fn name(&self) -> Option<String> {
+            Some(self.metadata.0.clone())
+        }
+
+        /// Returns the share token symbol
+        #[ink(message)]
+        pub fn symbol(&self) -> Option<String> {
+            Some(self.metadata.1.clone())
+        }
+
+        /// Returns the share token decimals
+        #[ink(message)]
+        pub fn decimals(&self) -> u8 {
+            self.metadata.2
+        }
+
+        /// Returns the underlying asset
+        #[ink(message)]
+        pub fn asset(&self) -> Address {
+            self.asset
+        }
+
+        /// Returns the amount of the underlying asset held by the vault
+        #[ink(message)]
+        pub fn total_assets(&self) -> u128 {
+            let asset: PspCoinRef = FromAddr::from_addr(self.asset);
+            asset.balance_of(self.env().address())
+        }
+
+        /// Returns the shares the vault would exchange for `assets`
+        #[ink(message)]
+        pub fn convert_to_shares(&self, assets: u128) -> u128 {
+            self.to_shares(assets, self.total_assets(), false)
+        }
+
+        /// Returns the assets the vault would exchange for `shares`
+        #[ink(message)]
+        pub fn convert_to_assets(&self, shares: u128) -> u128 {
+            self.to_assets(shares, self.total_assets(), false)
+        }
+
+        /// Returns the maximum assets `receiver` can deposit
+        #[ink(message)]
+        pub fn max_deposit(&self, _receiver: Address) -> u128 {
+            u128::MAX
+        }
+
+        /// Returns the maximum shares `receiver` can mint
+        #[ink(message)]
+        pub fn max_mint(&self, _receiver: Address) -> u128 {
+            u128::MAX
+        }
+
+        /// Returns the maximum assets `owner` can withdraw
+        #[ink(message)]
+        pub fn max_withdraw(&self, owner: Address) -> u128 {
+            self.convert_to_assets(self.balance_of(owner))
+        }
+
+        /// Returns the maximum shares `owner` can redeem
+        #[ink(message)]
+        pub fn max_redeem(&self, owner: Address) -> u128 {
+            self.balance_of(owner)
+        }
+
+        /// Returns the shares minted by depositing `assets`
+        #[ink(message)]
+        pub fn preview_deposit(&self, assets: u128) -> u128 {
+            self.to_shares(assets, self.total_assets(), false)
+        }
+
+        /// Returns the assets needed to mint `shares`
+        #[ink(message)]
+        pub fn preview_mint(&self, shares: u128) -> u128 {
+            self.to_assets(shares, self.total_assets(), true)
+        }
+
+        /// Returns the shares burned by withdrawing `assets`
+        #[ink(message)]
+        pub fn preview_withdraw(&self, assets: u128) -> u128 {
+            self.to_shares(assets, self.total_assets(), true)
+        }
+
+        /// Returns the assets received by redeeming `shares`
+        #[ink(message)]
+        pub fn preview_redeem(&self, shares: u128) -> u128 {
+            self.to_assets(shares, self.total_assets(), false)
+        }
+
+        /// Deposit `assets` from the caller and mint the shares to `receiver`
+        #[ink(message)]
+        pub fn deposit(&mut self, assets: u128, receiver: Address) -> Result<u128, PSP22Error> {
+            self.deposit_assets(receiver, assets, None)
+        }
+
+        /// Mint exactly `shares` to `receiver`, pulling the required assets from the caller
+        #[ink(message)]
+        pub fn mint(&mut self, shares: u128, receiver: Address) -> Result<u128, PSP22Error> {
+            let assets = self.preview_mint(shares);
+            self.deposit_assets(receiver, assets, Some(shares))?;
+            Ok(assets)
+        }
+
+        /// Burn `owner`'s shares to send exactly `assets` to `receiver`
+        #[ink(message)]
+        pub fn withdraw(
+            &mut self,
+            assets: u128,
+            receiver: Address,
+            owner: Address,
+        ) -> Result<u128, PSP22Error> {
+            let shares = self.preview_withdraw(assets);
+            self.withdraw_assets(receiver, owner, assets, shares)?;
+            Ok(shares)
+        }
+
+        /// Burn exactly `shares` of `owner` and send the assets to `receiver`
+        #[ink(message)]
+        pub fn redeem(
+            &mut self,
+            shares: u128,
+            receiver: Address,
+            owner: Address,
+        ) -> Result<u128, PSP22Error> {
+            let assets = self.preview_redeem(shares);
+            self.withdraw_assets(receiver, owner, assets, shares)?;
+            Ok(assets)
+        }
+    }
+
+    impl Vault {
+        /// Shares worth `assets`, with one virtual share and asset against inflation attacks
+        fn to_shares(&self, assets: u128, total_assets: u128, round_up: bool) -> u128 {
+            mul_div(
+                assets,
+                self.total_supply.saturating_add(1),
+                total_assets.saturating_add(1),
+                round_up,
+            )
+            .unwrap_or(u128::MAX)
+        }
+
+        /// Assets worth `shares`, with one virtual share and asset against inflation attacks
+        fn to_assets(&self, shares: u128, total_assets: u128, round_up: bool) -> u128 {
+            mul_div(
+                shares,
+                total_assets.saturating_add(1),
+                self.total_supply.saturating_add(1),
+                round_up,
+            )
+            .unwrap_or(u128::MAX)
+        }
+
+        /// Pull `assets` from the caller and mint `receiver` the shares the
+        /// amount received is worth, or exactly `exact_shares` if it covers
+        /// them, returning the shares minted
+        ///
+        /// Shares are priced on the balance change rather than `assets`, so
+        /// a transfer fee on the asset is borne by the depositor.
+        fn deposit_assets(
+            &mut self,
+            receiver: Address,
+            assets: u128,
+            exact_shares: Option<u128>,
+        ) -> Result<u128, PSP22Error> {
+            let sender = self.env().caller();
+            let this = self.env().address();
+
+            let total_assets = self.total_assets();
+            let mut asset: PspCoinRef = FromAddr::from_addr(self.asset);
+            asset.transfer_from(sender, this, assets, Vec::new())?;
+            let assets = asset.balance_of(this).saturating_sub(total_assets);
+
+            let mut shares = self.to_shares(assets, total_assets, false);
+            if let Some(exact_shares) = exact_shares {
+                if shares < exact_shares {
+                    return Err(PSP22Error::Custom(String::from("Insufficient assets received")));
+                }
+                shares = exact_shares;
+            }
+            if shares == 0 {
+                return Err(PSP22Error::Custom(String::from("Zero shares")));
+            }
+            self.mint_shares(receiver, shares)?;
+
+            self.env().emit_event(Deposit {
+                sender,
+                owner: receiver,
+                assets,
+                shares,
+            });
+
+            Ok(shares)
+        }
+
+        /// Burn `shares` of `owner` and send `assets` to `receiver`
+        fn withdraw_assets(
+            &mut self,
+            receiver: Address,
+            owner: Address,
+            assets: u128,
+            shares: u128,
+        ) -> Result<(), PSP22Error> {
+            let sender = self.env().caller();
+
+            self.spend_allowance(owner, sender, shares)?;
+            self.burn_shares(owner, shares)?;
+
+            let mut asset: PspCoinRef = FromAddr::from_addr(self.asset);
+            asset.transfer(receiver, assets, Vec::new())?;
+
+            self.env().emit_event(Withdraw {
+                sender,
+                receiver,
+                owner,
+                assets,
+                shares,
+            });
+
+            Ok(())
+        }
+
+        /// Consume `value` of the allowance `owner` granted to `spender`
+        fn spend_allowance(&mut self, owner: Address, spender: Address, value: u128) -> Result<(), PSP22Error> {
+            if owner == spender {
+                return Ok(());
+            }
+
+            let new_allowance = self
+                .allowance(owner, spender)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientAllowance)?;
+            self.allowances.insert((owner, spender), &new_allowance);
+
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value: new_allowance,
+            });
+
+            Ok(())
+        }
+
+        /// Move `value` shares between two accounts
+        fn transfer_shares(&mut self, from: Address, to: Address, value: u128) -> Result<(), PSP22Error> {
+            if from == to || value == 0 {
+                return Ok(());
+            }
+
+            let new_from_balance = self
+                .balance_of(from)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            let new_to_balance = self
+                .balance_of(to)
+                .checked_add(value)
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+
+            self.balances.insert(from, &new_from_balance);
+            self.balances.insert(to, &new_to_balance);
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Create `value` new shares on `to`'s account
+        fn mint_shares(&mut self, to: Address, value: u128) -> Result<(), PSP22Error> {
+            self.total_supply = self
+                .total_supply
+                .checked_add(value)
+                .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+            self.balances.insert(to, &(self.balance_of(to) + value));
+
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Destroy `value` shares held by `from`
+        fn burn_shares(&mut self, from: Address, value: u128) -> Result<(), PSP22Error> {
+            let new_balance = self
+                .balance_of(from)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.balances.insert(from, &new_balance);
+            self.total_supply -= value;
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: None,
+                value,
+            });
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn mul_div_rounds_as_requested() {
+            assert_eq!(mul_div(10, 3, 4, false), Some(7));
+            assert_eq!(mul_div(10, 3, 4, true), Some(8));
+            assert_eq!(mul_div(u128::MAX, 2, 2, false), Some(u128::MAX));
+            assert_eq!(mul_div(u128::MAX, 2, 1, false), None);
+            assert_eq!(mul_div(1, 1, 0, false), None);
+        }
+
+        #[ink::test]
+        fn share_math_uses_virtual_offset() {
+            let accounts = ink::env::test::default_accounts();
+            let mut vault = Vault::new(accounts.django, String::from("Vault"), String::from("vPSP"), 18);
+
+            // Empty vault exchanges one to one
+            assert_eq!(vault.to_shares(100, 0, false), 100);
+
+            // 100 shares backed by 200 assets
+            vault.total_supply = 100;
+            assert_eq!(vault.to_shares(200, 200, false), 100);
+            assert_eq!(vault.to_assets(50, 200, false), 99);
+            assert_eq!(vault.to_shares(1, 200, true), 1);
+        }
+
+        #[ink::test]
+        fn share_transfers_work() {
+            let accounts = ink::env::test::default_accounts();
+            ink::env::test::set_caller(accounts.alice);
+
+            let mut vault = Vault::new(accounts.django, String::from("Vault"), String::from("vPSP"), 18);
+            assert_eq!(vault.mint_shares(accounts.alice, 100), Ok(()));
+
+            assert_eq!(vault.approve(accounts.bob, 40), Ok(()));
+            ink::env::test::set_caller(accounts.bob);
+            assert_eq!(
+                vault.transfer_from(accounts.alice, accounts.charlie, 40, vec![]),
+                Ok(())
+            );
+            assert_eq!(
+                vault.transfer_from(accounts.alice, accounts.charlie, 1, vec![]),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(vault.balance_of(accounts.charlie), 40);
+            assert_eq!(vault.max_redeem(accounts.alice), 60);
+        }
+    }
+}
@@ -1,14 +1,445 @@
-use ink::prelude::string::String;
-
-/// PSP-22 Error types following the standard
-#[derive(Debug, PartialEq, Eq)]
-#[ink::scale_derive(Encode, Decode, TypeInfo)]
-#[allow(clippy::cast_possible_truncation)]
-pub enum PSP22Error {
-    /// Insufficient balance for transfer
-    InsufficientBalance,
-    /// Insufficient allowance for transfer_from
-    InsufficientAllowance,
-    /// Custom error with message
-    Custom(String),
+use ink::prelude::{string::String, vec::Vec};
+use ink::{Address, U256};
+
+pub use psp22_interface::PSP22Error;
+
+/// Dividends paid in a PSP22 token to holders as of a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct DividendRound {
+    /// PSP22 token the dividends are paid in
+    pub asset: Address,
+    /// Amount of `asset` shared among holders
+    pub amount: u128,
+    /// Snapshot whose balances determine each holder's share
+    pub snapshot_id: u32,
+}
+
+/// Price curve along which tokens are minted for and burned against native currency
+///
+/// Prices are in native currency per whole token (`10^decimals` base units).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub enum BondingCurve {
+    /// Price grows by `slope` for every whole token in supply
+    Linear { base_price: U256, slope: U256 },
+    /// Price grows by `growth_bps` for every `step` base units in supply
+    Exponential {
+        base_price: U256,
+        growth_bps: u16,
+        step: u128,
+    },
+}
+
+/// Tokens streamed from `sender` to `recipient` at a constant rate per second
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Stream {
+    /// Account that funded the stream
+    pub sender: Address,
+    /// Account the tokens stream to
+    pub recipient: Address,
+    /// Tokens escrowed for the whole stream
+    pub deposit: u128,
+    /// Time the stream starts
+    pub start: u64,
+    /// Time the stream ends, with the whole deposit streamed
+    pub stop: u64,
+    /// Tokens the recipient has already withdrawn
+    pub withdrawn: u128,
+}
+
+/// Authorization for `merchant` to pull `amount` from `subscriber` once per period
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Subscription {
+    /// Account paying for the subscription
+    pub subscriber: Address,
+    /// Account allowed to charge the subscription
+    pub merchant: Address,
+    /// Tokens charged per period
+    pub amount: u128,
+    /// Length of a billing period
+    pub period: u64,
+    /// Earliest time of the next charge
+    pub next_charge_at: u64,
+    /// Tokens the merchant may still charge in total
+    pub remaining: u128,
+}
+
+/// Lifecycle of an escrow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub enum EscrowState {
+    /// Tokens are locked awaiting release, resolution or timeout
+    Pending,
+    /// Tokens were paid to the seller
+    Released,
+    /// Tokens were returned to the buyer
+    Refunded,
+}
+
+/// Tokens locked by `buyer` until paid to `seller` or refunded
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct Escrow {
+    /// Account that locked the tokens
+    pub buyer: Address,
+    /// Account paid when the escrow is released
+    pub seller: Address,
+    /// Account allowed to settle the escrow either way
+    pub arbiter: Address,
+    /// Tokens locked in the escrow
+    pub amount: u128,
+    /// Time after which the buyer can reclaim a pending escrow
+    pub deadline: u64,
+    /// Current state of the escrow
+    pub state: EscrowState,
+}
+
+/// Tokens claimable by `receiver` with the preimage of `hashlock` until `timelock`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct HashedTimelock {
+    /// Account that locked the tokens and gets them back after `timelock`
+    pub sender: Address,
+    /// Account that can withdraw the tokens with the preimage
+    pub receiver: Address,
+    /// Tokens locked
+    pub amount: u128,
+    /// SHA-256 hash of the secret preimage
+    pub hashlock: [u8; 32],
+    /// Time from which the sender can refund the tokens
+    pub timelock: u64,
+    /// Preimage revealed by the receiver's withdrawal
+    pub preimage: Option<Vec<u8>>,
+    /// Whether the sender refunded the tokens
+    pub refunded: bool,
+}
+
+/// Tokens an account locked in place until `unlock_at`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct TokenLock {
+    /// Id of the lock, unique across accounts
+    pub id: u32,
+    /// Tokens locked
+    pub amount: u128,
+    /// Time from which the lock can be released
+    pub unlock_at: u64,
+}
+
+/// Return value of a call dispatched by `multicall`, decoded from a
+/// message returning `Result<T, PSP22Error>` for any `T`
+///
+/// The encoded `T` is kept as raw bytes since batched messages return
+/// different types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BatchCallOutput(pub Result<Vec<u8>, PSP22Error>);
+
+impl ink::scale::Decode for BatchCallOutput {
+    fn decode<I: ink::scale::Input>(input: &mut I) -> Result<Self, ink::scale::Error> {
+        match input.read_byte()? {
+            0 => {
+                let mut value = ink::prelude::vec![0u8; input.remaining_len()?.unwrap_or(0)];
+                input.read(&mut value)?;
+                Ok(Self(Ok(value)))
+            }
+            1 => Ok(Self(Err(<PSP22Error as ink::scale::Decode>::decode(input)?))),
+            _ => Err("Batched call did not return a Result".into()),
+        }
+    }
+}
+
+/// Arguments of a call dispatched by `multicall`, already SCALE encoded
+/// and written out as-is
+pub(crate) struct EncodedArgs<'a>(pub &'a [u8]);
+
+impl ink::scale::Encode for EncodedArgs<'_> {
+    fn size_hint(&self) -> usize {
+        self.0.len()
+    }
+
+    fn encode_to<T: ink::scale::Output + ?Sized>(&self, dest: &mut T) {
+        dest.write(self.0);
+    }
+}
+
+/// Cap on how much a spender can pull from an owner per period
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct SpendingLimit {
+    /// Tokens the spender can pull per period
+    pub limit: u128,
+    /// Length of a period
+    pub period: u64,
+    /// Start of the current period
+    pub window_start: u64,
+    /// Tokens pulled in the current period
+    pub spent: u128,
+}
+
+/// Signed approval submitted on the owner's behalf
+///
+/// The signature covers the Keccak-256 hash of the SCALE encoded tuple
+/// `(b"PSP22Permit::permit", token, owner, spender, value, nonce, deadline)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub struct Permit {
+    /// Account granting the allowance and signing the permit
+    pub owner: Address,
+    /// Account receiving the allowance
+    pub spender: Address,
+    /// Allowance granted
+    pub value: u128,
+    /// Owner's nonce the permit consumes
+    pub nonce: u64,
+    /// Time after which the permit can no longer be submitted
+    pub deadline: u64,
+    /// Owner's signature over the permit
+    pub signature: [u8; 65],
+}
+
+/// Deployment parameters for `PspCoin::new_with_config`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub struct TokenConfig {
+    /// Token name
+    pub name: String,
+    /// Token symbol
+    pub symbol: String,
+    /// Token decimals
+    pub decimals: u8,
+    /// Tokens minted to `owner` at deployment
+    pub initial_supply: u128,
+    /// Upper bound for the total supply, if any (requires `capped`)
+    pub cap: Option<u128>,
+    /// Account holding every administrative role and the initial supply
+    pub owner: Address,
+    /// Transfer fee in basis points (requires `fees`)
+    pub fee_bps: u16,
+    /// Account receiving transfer fees, defaulting to `owner`
+    pub treasury: Option<Address>,
+    /// Whether the token starts paused (requires `pausable`)
+    pub paused: bool,
+}
+
+/// Name, symbol and decimals of the token
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct TokenMetadata {
+    /// Token name
+    pub name: String,
+    /// Token symbol
+    pub symbol: String,
+    /// Token decimals, fixed at deployment
+    pub decimals: u8,
+}
+
+/// Tokens minted to `recipient` over time, at a per-block rate that halves every `halving_interval` blocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct EmissionSchedule {
+    /// Account `drip` mints the accrued tokens to
+    pub recipient: Address,
+    /// Block the emission starts at
+    pub start_block: u32,
+    /// Tokens emitted per block until the first halving
+    pub initial_rate: u128,
+    /// Blocks between two halvings of the rate
+    pub halving_interval: u32,
+    /// Most tokens the schedule ever emits
+    pub max_emission: u128,
+}
+
+/// Mint announced by the owner, executable once `execute_after` has passed
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct QueuedMint {
+    /// Account the tokens are minted to
+    pub to: Address,
+    /// Tokens to mint
+    pub amount: u128,
+    /// Time from which the mint can be executed
+    pub execute_after: u64,
+}
+
+/// Sensitive call that, once approvals are configured, needs confirmations from the approval signers
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub enum PrivilegedAction {
+    /// Mint `amount` to `to`, without going through the mint queue
+    Mint { to: Address, amount: u128 },
+    /// Halt transfers between holders
+    Pause,
+    /// Resume transfers between holders
+    Unpause,
+    /// Set the transfer fee in basis points
+    SetFee { fee_bps: u16 },
+    /// Replace the approval signers, threshold and proposal lifetime;
+    /// a zero threshold turns approvals off
+    ConfigureApprovals {
+        signers: Vec<Address>,
+        threshold: u32,
+        window: u64,
+    },
+}
+
+/// Privileged action proposed by an approval signer
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct ActionProposal {
+    /// Action executed once enough signers confirmed it
+    pub action: PrivilegedAction,
+    /// Signer that proposed the action
+    pub proposer: Address,
+    /// Time after which the proposal can no longer be confirmed or executed
+    pub expires_at: u64,
+    /// Whether the action was executed
+    pub executed: bool,
+}
+
+/// Large transfer announced by `from`, executable once `execute_after` has passed
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct AnnouncedTransfer {
+    /// Account sending the tokens
+    pub from: Address,
+    /// Account receiving the tokens
+    pub to: Address,
+    /// Tokens to transfer
+    pub amount: u128,
+    /// Time from which the sender can execute the transfer
+    pub execute_after: u64,
+}
+
+/// Cumulative transfers of an account, kept with the `transfer-stats` feature
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct AccountStats {
+    /// Tokens sent, including transfer fees
+    pub total_sent: u128,
+    /// Tokens received, after transfer fees
+    pub total_received: u128,
+    /// Number of transfers sent
+    pub sent_count: u32,
+    /// Number of transfers received
+    pub received_count: u32,
+}
+
+/// Transfer between holders kept in the recent-transfer ring buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct TransferRecord {
+    /// Account that sent the tokens
+    pub from: Address,
+    /// Account that received the tokens
+    pub to: Address,
+    /// Tokens received, after any transfer fee
+    pub value: u128,
+    /// Block the transfer happened in
+    pub block: u32,
+}
+
+/// Lottery round drawing one holder, weighted by balance, to win `prize`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct LotteryRound {
+    /// Tokens minted to the winner when claimed
+    pub prize: u128,
+    /// Keccak-256 hash of the secret the owner reveals to draw the round
+    pub commitment: [u8; 32],
+    /// Block the round was started in; it can be drawn from the next block
+    pub started_at: u32,
+    /// Holder drawn as winner, once drawn
+    pub winner: Option<Address>,
+    /// Whether the winner claimed the prize
+    pub claimed: bool,
+}
+
+/// Oracle reporting the token's USD price, and the rate used when it cannot
+///
+/// Prices are in USD per whole token (`10^decimals` base units), scaled by
+/// `10^18`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct PriceFeed {
+    /// DIA-style oracle implementing `get_latest_price(key) -> Option<(u64, u128)>`
+    pub oracle: Address,
+    /// Pair the oracle is queried for, e.g. `"PSP/USD"`
+    pub key: String,
+    /// Oldest quote accepted, in milliseconds behind the block timestamp
+    pub max_staleness: u64,
+    /// Price used when the oracle fails or its quote is stale, if any
+    pub fallback_price: Option<u128>,
+}
+
+/// Contract consulted on every holder transfer, before and/or after balances move
+///
+/// Before-transfer hooks implement `can_transfer(from, to, value) -> Result<(), u32>`
+/// and after-transfer hooks `after_transfer(from, to, value) -> Result<(), u32>`;
+/// an `Err` from either vetoes the transfer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+pub struct TransferHook {
+    /// Hook contract
+    pub contract: Address,
+    /// Whether the hook is called before balances move
+    pub before: bool,
+    /// Whether the hook is called after balances move
+    pub after: bool,
+}
+
+/// Branding a wallet can show for the token, as returned by `metadata_uri`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub struct MetadataUri {
+    /// URI of the token logo or of a JSON document describing the token
+    pub token_uri: Option<String>,
+    /// Project links such as `("website", "https://...")`, in the order set
+    pub links: Vec<(String, String)>,
+}
+
+/// Summary of the token's state and configuration, as returned by `contract_info`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub struct ContractInfo {
+    /// Version of the `psp_coin` crate the contract was built from
+    pub version: String,
+    /// Name, symbol and decimals
+    pub metadata: TokenMetadata,
+    /// Current total supply
+    pub total_supply: u128,
+    /// Account allowed to change the token configuration
+    pub owner: Address,
+    /// Whether transfers between holders are halted
+    pub paused: bool,
+    /// Upper bound for the total supply, if any
+    pub cap: Option<u128>,
+    /// Transfer fee in basis points
+    pub fee_bps: u16,
+    /// Account receiving transfer fees
+    pub treasury: Address,
+    /// Flash loan fee in basis points
+    pub flash_fee_bps: u16,
 }
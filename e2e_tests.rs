@@ -0,0 +1,171 @@
+//! End-to-end tests of the PSP22 surface against a running node
+//!
+//! Unlike the off-chain unit tests these go through real dispatch, so
+//! they also cover selectors, event encoding and reverts of failed calls.
+
+use ink::primitives::AccountId;
+use ink::scale::Decode;
+use ink_e2e::ContractsBackend;
+
+use crate::psp_coin::{Approval, Transfer};
+use crate::{PSP22Error, PspCoin, PspCoinRef};
+
+type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[ink_e2e::test]
+async fn transfer_moves_balances<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+    // Given alice holds the whole supply
+    let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+    let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+    let mut constructor = PspCoinRef::new_with_supply(1_000);
+    let token = client
+        .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+        .submit()
+        .await
+        .expect("token instantiate failed");
+    let mut call = token.call_builder::<PspCoin>();
+
+    // When alice sends 300 tokens to bob
+    let result = client
+        .call(&ink_e2e::alice(), &call.transfer(bob, 300, Vec::new()))
+        .submit()
+        .await?;
+
+    // Then both balances and the supply reflect it
+    let events = result.contract_emitted_events()?;
+    let transfer = Transfer::decode(&mut &events[0].event.data[..])?;
+    assert_eq!((transfer.from, transfer.to, transfer.value), (Some(alice), Some(bob), 300));
+
+    let alice_balance = client
+        .call(&ink_e2e::alice(), &call.balance_of(alice))
+        .dry_run()
+        .await?;
+    assert_eq!(alice_balance.return_value(), 700);
+    let bob_balance = client
+        .call(&ink_e2e::alice(), &call.balance_of(bob))
+        .dry_run()
+        .await?;
+    assert_eq!(bob_balance.return_value(), 300);
+    let supply = client
+        .call(&ink_e2e::alice(), &call.total_supply())
+        .dry_run()
+        .await?;
+    assert_eq!(supply.return_value(), 1_000);
+
+    // And bob cannot send more than was received
+    let overdrawn = client
+        .call(&ink_e2e::bob(), &call.transfer(alice, 301, Vec::new()))
+        .dry_run()
+        .await?
+        .return_value();
+    assert_eq!(overdrawn, Err(PSP22Error::InsufficientBalance));
+
+    Ok(())
+}
+
+#[ink_e2e::test]
+async fn transfer_from_spends_allowance<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+    // Given alice approved bob for 200 tokens
+    let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+    let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+    let charlie = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::charlie());
+
+    let mut constructor = PspCoinRef::new_with_supply(1_000);
+    let token = client
+        .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+        .submit()
+        .await
+        .expect("token instantiate failed");
+    let mut call = token.call_builder::<PspCoin>();
+
+    let approved = client
+        .call(&ink_e2e::alice(), &call.approve(bob, 200))
+        .submit()
+        .await?;
+    let events = approved.contract_emitted_events()?;
+    let approval = Approval::decode(&mut &events[0].event.data[..])?;
+    assert_eq!((approval.owner, approval.spender, approval.value), (alice, bob, 200));
+
+    // When bob moves 150 of alice's tokens to charlie
+    let spent = client
+        .call(&ink_e2e::bob(), &call.transfer_from(alice, charlie, 150, Vec::new()))
+        .submit()
+        .await?;
+
+    // Then the allowance shrinks and an `Approval` reports what is left
+    let events = spent.contract_emitted_events()?;
+    let approval = Approval::decode(&mut &events[0].event.data[..])?;
+    assert_eq!(approval.value, 50);
+
+    let allowance = client
+        .call(&ink_e2e::bob(), &call.allowance(alice, bob))
+        .dry_run()
+        .await?;
+    assert_eq!(allowance.return_value(), 50);
+    let charlie_balance = client
+        .call(&ink_e2e::bob(), &call.balance_of(charlie))
+        .dry_run()
+        .await?;
+    assert_eq!(charlie_balance.return_value(), 150);
+
+    // And bob cannot spend beyond what is left
+    let exceeded = client
+        .call(&ink_e2e::bob(), &call.transfer_from(alice, charlie, 51, Vec::new()))
+        .dry_run()
+        .await?
+        .return_value();
+    assert_eq!(exceeded, Err(PSP22Error::InsufficientAllowance));
+
+    Ok(())
+}
+
+#[ink_e2e::test]
+async fn mint_and_burn_across_signers<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+    // Given a token owned by alice
+    let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+    let mut constructor = PspCoinRef::new_with_supply(1_000);
+    let token = client
+        .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+        .submit()
+        .await
+        .expect("token instantiate failed");
+    let mut call = token.call_builder::<PspCoin>();
+
+    // When alice mints to bob, while bob cannot mint at all
+    let minted = client
+        .call(&ink_e2e::alice(), &call.mint_to(bob, 500))
+        .submit()
+        .await?;
+    let events = minted.contract_emitted_events()?;
+    let transfer = Transfer::decode(&mut &events[0].event.data[..])?;
+    assert_eq!((transfer.from, transfer.to, transfer.value), (None, Some(bob), 500));
+
+    let refused = client
+        .call(&ink_e2e::bob(), &call.mint_to(bob, 500))
+        .dry_run()
+        .await?
+        .return_value();
+    assert_eq!(refused, Err(PSP22Error::Unauthorized));
+
+    // And bob burns part of the minted tokens
+    client
+        .call(&ink_e2e::bob(), &call.burn(200))
+        .submit()
+        .await?;
+
+    // Then bob's balance and the supply account for both
+    let balance = client
+        .call(&ink_e2e::bob(), &call.balance_of(bob))
+        .dry_run()
+        .await?;
+    assert_eq!(balance.return_value(), 300);
+    let supply = client
+        .call(&ink_e2e::bob(), &call.total_supply())
+        .dry_run()
+        .await?;
+    assert_eq!(supply.return_value(), 1_300);
+
+    Ok(())
+}
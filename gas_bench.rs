@@ -0,0 +1,146 @@
+//! Gas benchmarks of the hot PSP22 paths against a running node
+//!
+//! Each benchmark fails once the `ref_time` or `proof_size` a call consumed
+//! exceeds its budget, reporting the consumption in the failure, so an
+//! extension that slows down transfers shows up as a failing test rather
+//! than higher fees. Budgets leave headroom over the measured consumption;
+//! lower them when a path gets cheaper so the headroom does not hide the
+//! next regression.
+
+use ink::primitives::AccountId;
+use ink::scale::Encode;
+use ink_e2e::ContractsBackend;
+
+use crate::{PspCoin, PspCoinRef};
+
+type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Upper bound on the cost of a call, in weight units
+struct Budget {
+    ref_time: u64,
+    proof_size: u64,
+}
+
+const TRANSFER_BUDGET: Budget = Budget {
+    ref_time: 2_500_000_000,
+    proof_size: 120_000,
+};
+
+const TRANSFER_FROM_BUDGET: Budget = Budget {
+    ref_time: 3_000_000_000,
+    proof_size: 140_000,
+};
+
+/// Budget for `approve_batch` with `BATCH_SIZE` entries
+const APPROVE_BATCH_BUDGET: Budget = Budget {
+    ref_time: 8_000_000_000,
+    proof_size: 250_000,
+};
+
+/// Budget for `multicall` with `BATCH_SIZE` transfers
+const MULTICALL_BUDGET: Budget = Budget {
+    ref_time: 15_000_000_000,
+    proof_size: 400_000,
+};
+
+/// Entries in each benchmarked batch
+const BATCH_SIZE: usize = 8;
+
+/// Fail with the weight a call consumed if it is over budget
+fn check_budget(name: &str, ref_time: u64, proof_size: u64, budget: &Budget) {
+    assert!(
+        ref_time <= budget.ref_time,
+        "{name} consumed ref_time {ref_time}, over its budget of {}",
+        budget.ref_time
+    );
+    assert!(
+        proof_size <= budget.proof_size,
+        "{name} consumed proof_size {proof_size}, over its budget of {}",
+        budget.proof_size
+    );
+}
+
+#[ink_e2e::test]
+async fn transfer_paths_stay_within_budget<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+    let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+    let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+    let charlie = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::charlie());
+
+    let mut constructor = PspCoinRef::new_with_supply(1_000_000);
+    let token = client
+        .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+        .submit()
+        .await
+        .expect("token instantiate failed");
+    let mut call = token.call_builder::<PspCoin>();
+
+    // Recipients already hold tokens, so the benchmarks measure the
+    // steady state rather than first-time storage allocation
+    client
+        .call(&ink_e2e::alice(), &call.transfer(bob, 1_000, Vec::new()))
+        .submit()
+        .await?;
+    client
+        .call(&ink_e2e::alice(), &call.transfer(charlie, 1_000, Vec::new()))
+        .submit()
+        .await?;
+    client
+        .call(&ink_e2e::alice(), &call.approve(bob, 10_000))
+        .submit()
+        .await?;
+
+    let transfer = client
+        .call(&ink_e2e::alice(), &call.transfer(bob, 100, Vec::new()))
+        .dry_run()
+        .await?;
+    let consumed = transfer.exec_result.gas_consumed;
+    check_budget("transfer", consumed.ref_time(), consumed.proof_size(), &TRANSFER_BUDGET);
+
+    let transfer_from = client
+        .call(&ink_e2e::bob(), &call.transfer_from(alice, charlie, 100, Vec::new()))
+        .dry_run()
+        .await?;
+    let consumed = transfer_from.exec_result.gas_consumed;
+    check_budget("transfer_from", consumed.ref_time(), consumed.proof_size(), &TRANSFER_FROM_BUDGET);
+
+    Ok(())
+}
+
+#[ink_e2e::test]
+async fn batch_paths_stay_within_budget<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+    let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+    let mut constructor = PspCoinRef::new_with_supply(1_000_000);
+    let token = client
+        .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+        .submit()
+        .await
+        .expect("token instantiate failed");
+    let mut call = token.call_builder::<PspCoin>();
+
+    let approvals = (1..=BATCH_SIZE as u128).map(|value| (bob, value)).collect::<Vec<_>>();
+    let approve_batch = client
+        .call(&ink_e2e::alice(), &call.approve_batch(approvals))
+        .dry_run()
+        .await?;
+    let consumed = approve_batch.exec_result.gas_consumed;
+    check_budget("approve_batch", consumed.ref_time(), consumed.proof_size(), &APPROVE_BATCH_BUDGET);
+
+    // `multicall` takes each call as its selector followed by its arguments
+    let transfer_selector = [0xDB, 0x20, 0xF9, 0xF5];
+    let calls = (0..BATCH_SIZE)
+        .map(|_| {
+            let mut encoded = transfer_selector.to_vec();
+            (bob, 10u128, Vec::<u8>::new()).encode_to(&mut encoded);
+            encoded
+        })
+        .collect::<Vec<_>>();
+    let multicall = client
+        .call(&ink_e2e::alice(), &call.multicall(calls))
+        .dry_run()
+        .await?;
+    let consumed = multicall.exec_result.gas_consumed;
+    check_budget("multicall", consumed.ref_time(), consumed.proof_size(), &MULTICALL_BUDGET);
+
+    Ok(())
+}
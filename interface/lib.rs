@@ -0,0 +1,188 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Interface of a PSP22 token for contracts that call one
+//!
+//! Depend on this crate instead of `psp_coin` to call any PSP22 token
+//! without compiling its implementation:
+//!
+//! ```ignore
+//! let mut token: PSP22Ref = token_address.into();
+//! token.transfer(recipient, amount, Vec::new())?;
+//! ```
+
+use ink::prelude::{string::String, vec::Vec};
+use ink::Address;
+
+/// PSP-22 Error types following the standard
+///
+/// The first six variants keep the standard's order so their SCALE
+/// encoding matches other PSP22 tokens; extensions are appended after them.
+#[derive(Debug, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+#[allow(clippy::cast_possible_truncation)]
+pub enum PSP22Error {
+    /// Custom error with message
+    Custom(String),
+    /// Insufficient balance for transfer
+    InsufficientBalance,
+    /// Insufficient allowance for transfer_from
+    InsufficientAllowance,
+    /// Recipient is the zero address
+    ZeroRecipientAddress,
+    /// Sender is the zero address
+    ZeroSenderAddress,
+    /// Recipient contract rejected a safe transfer
+    SafeTransferCheckFailed(String),
+    /// Caller is not allowed to perform this action
+    Unauthorized,
+    /// Sender or recipient account is frozen
+    Frozen,
+    /// Sender or recipient is not on the allowlist
+    NotAllowlisted,
+    /// Trading has not opened yet
+    TradingNotEnabled,
+    /// Sender transferred too recently
+    RateLimited,
+    /// Transfer amount exceeds the per-transaction limit
+    MaxTxExceeded,
+    /// Recipient balance would exceed the per-wallet limit
+    MaxWalletExceeded,
+    /// Signature could not be verified
+    InvalidSignature,
+    /// Tokens are staked or otherwise locked
+    BalanceLocked,
+    /// Allowance differs from the value the caller expected
+    AllowanceChanged,
+    /// A guarded message was re-entered during a cross-contract call
+    ReentrancyDetected,
+    /// Sender or recipient is not verified by the KYC registry
+    NotVerified,
+}
+
+impl PSP22Error {
+    /// Stable numeric code of the error, independent of variant order
+    ///
+    /// Codes below 100 follow the PSP22 standard errors; extension errors
+    /// start at 100. The message of `Custom` and `SafeTransferCheckFailed`
+    /// is not part of the code.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::Custom(_) => 0,
+            Self::InsufficientBalance => 1,
+            Self::InsufficientAllowance => 2,
+            Self::ZeroRecipientAddress => 3,
+            Self::ZeroSenderAddress => 4,
+            Self::SafeTransferCheckFailed(_) => 5,
+            Self::Unauthorized => 100,
+            Self::Frozen => 101,
+            Self::NotAllowlisted => 102,
+            Self::TradingNotEnabled => 103,
+            Self::RateLimited => 104,
+            Self::MaxTxExceeded => 105,
+            Self::MaxWalletExceeded => 106,
+            Self::InvalidSignature => 107,
+            Self::BalanceLocked => 108,
+            Self::AllowanceChanged => 109,
+            Self::ReentrancyDetected => 110,
+            Self::NotVerified => 111,
+        }
+    }
+}
+
+impl From<PSP22Error> for u32 {
+    fn from(error: PSP22Error) -> Self {
+        error.code()
+    }
+}
+
+impl From<u32> for PSP22Error {
+    /// Rebuild the error with code `code`; messages are lost, so
+    /// `Custom` and `SafeTransferCheckFailed` come back empty and unknown
+    /// codes map to `Custom`
+    fn from(code: u32) -> Self {
+        match code {
+            1 => Self::InsufficientBalance,
+            2 => Self::InsufficientAllowance,
+            3 => Self::ZeroRecipientAddress,
+            4 => Self::ZeroSenderAddress,
+            5 => Self::SafeTransferCheckFailed(String::new()),
+            100 => Self::Unauthorized,
+            101 => Self::Frozen,
+            102 => Self::NotAllowlisted,
+            103 => Self::TradingNotEnabled,
+            104 => Self::RateLimited,
+            105 => Self::MaxTxExceeded,
+            106 => Self::MaxWalletExceeded,
+            107 => Self::InvalidSignature,
+            108 => Self::BalanceLocked,
+            109 => Self::AllowanceChanged,
+            110 => Self::ReentrancyDetected,
+            111 => Self::NotVerified,
+            _ => Self::Custom(String::new()),
+        }
+    }
+}
+
+/// Standard PSP22 messages, with the selectors every PSP22 token uses
+#[ink::trait_definition]
+pub trait PSP22 {
+    /// Returns the total token supply
+    #[ink(message, selector = 0x162DF8C2)]
+    fn total_supply(&self) -> u128;
+
+    /// Returns the balance of `owner`
+    #[ink(message, selector = 0x6568382F)]
+    fn balance_of(&self, owner: Address) -> u128;
+
+    /// Returns the amount `spender` may still transfer from `owner`
+    #[ink(message, selector = 0x4D47D921)]
+    fn allowance(&self, owner: Address, spender: Address) -> u128;
+
+    /// Transfer `value` tokens from the caller to `to`
+    #[ink(message, selector = 0xDB20F9F5)]
+    fn transfer(&mut self, to: Address, value: u128, data: Vec<u8>) -> Result<(), PSP22Error>;
+
+    /// Transfer `value` tokens from `from` to `to` using the caller's allowance
+    #[ink(message, selector = 0x54B3C76E)]
+    fn transfer_from(
+        &mut self,
+        from: Address,
+        to: Address,
+        value: u128,
+        data: Vec<u8>,
+    ) -> Result<(), PSP22Error>;
+
+    /// Allow `spender` to transfer up to `value` of the caller's tokens
+    #[ink(message, selector = 0xB20F1BBD)]
+    fn approve(&mut self, spender: Address, value: u128) -> Result<(), PSP22Error>;
+
+    /// Raise the allowance granted to `spender` by `delta_value`
+    #[ink(message, selector = 0x96D6B57A)]
+    fn increase_allowance(&mut self, spender: Address, delta_value: u128) -> Result<(), PSP22Error>;
+
+    /// Lower the allowance granted to `spender` by `delta_value`
+    #[ink(message, selector = 0xFECB57D5)]
+    fn decrease_allowance(&mut self, spender: Address, delta_value: u128) -> Result<(), PSP22Error>;
+}
+
+/// Optional PSP22 metadata messages
+#[ink::trait_definition]
+pub trait PSP22Metadata {
+    /// Returns the token name
+    #[ink(message, selector = 0x3D261BD4)]
+    fn name(&self) -> Option<String>;
+
+    /// Returns the token symbol
+    #[ink(message, selector = 0x34205BE5)]
+    fn symbol(&self) -> Option<String>;
+
+    /// Returns the token decimals
+    #[ink(message, selector = 0x7271B782)]
+    fn decimals(&self) -> u8;
+}
+
+/// Client for calling the `PSP22` messages of the token at an address
+pub type PSP22Ref = ink::contract_ref!(PSP22);
+
+/// Client for calling the `PSP22Metadata` messages of the token at an address
+pub type PSP22MetadataRef = ink::contract_ref!(PSP22Metadata);
@@ -0,0 +1,32 @@
+//! `u128` arithmetic computed through `U256` intermediates
+//!
+//! Products of two 18-decimal amounts overflow `u128` long before the
+//! quotient does, so fee, reward and share math multiplies in `U256` and
+//! only fails (or saturates) when the final result does not fit.
+
+use ink::U256;
+
+/// `a * b / denominator` rounded down, or `None` if `denominator` is zero
+/// or the result exceeds `u128::MAX`
+pub(crate) fn mul_div(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    u128::try_from(U256::from(a) * U256::from(b) / U256::from(denominator)).ok()
+}
+
+/// `amount` with `from_decimals` decimals expressed with `to_decimals`
+/// decimals, rounded down, or `None` if it does not fit in `u128`
+pub(crate) fn rescale(amount: u128, from_decimals: u8, to_decimals: u8) -> Option<u128> {
+    if to_decimals >= from_decimals {
+        10u128
+            .checked_pow(u32::from(to_decimals - from_decimals))
+            .and_then(|factor| amount.checked_mul(factor))
+    } else {
+        Some(
+            10u128
+                .checked_pow(u32::from(from_decimals - to_decimals))
+                .map_or(0, |factor| amount / factor),
+        )
+    }
+}
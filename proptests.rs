@@ -0,0 +1,241 @@
+//! Property tests running random sequences of PSP22 calls against the
+//! token and a `BTreeMap` reference model, requiring identical results
+//! and state after every call
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+use ink::Address;
+use ink::prelude::string::String;
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestRunner};
+
+use crate::{PSP22Error, PspCoin};
+
+/// Number of accounts calls are made between; the first deploys and owns the token
+const ACCOUNTS: usize = 4;
+
+/// Supply minted to the deployer
+const INITIAL_SUPPLY: u128 = 1_000_000;
+
+/// A call made by one of the test accounts, identified by index
+#[derive(Debug, Clone)]
+enum Op {
+    Transfer { from: usize, to: usize, value: u128 },
+    Approve { owner: usize, spender: usize, value: u128 },
+    TransferFrom { spender: usize, from: usize, to: usize, value: u128 },
+    Mint { to: usize, value: u128 },
+    Burn { from: usize, value: u128 },
+}
+
+fn account() -> impl Strategy<Value = usize> {
+    0..ACCOUNTS
+}
+
+/// Small amounts, which mostly succeed, mixed with values at the top of
+/// the `u128` range, which probe the overflow and infinite allowance paths
+fn value() -> impl Strategy<Value = u128> {
+    prop_oneof![
+        4 => 0..=INITIAL_SUPPLY,
+        1 => Just(u128::MAX),
+        1 => (1..=2u128).prop_map(|offset| u128::MAX - offset),
+        1 => any::<u128>(),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (account(), account(), value()).prop_map(|(from, to, value)| Op::Transfer { from, to, value }),
+        (account(), account(), value()).prop_map(|(owner, spender, value)| Op::Approve { owner, spender, value }),
+        (account(), account(), account(), value())
+            .prop_map(|(spender, from, to, value)| Op::TransferFrom { spender, from, to, value }),
+        (account(), value()).prop_map(|(to, value)| Op::Mint { to, value }),
+        (account(), value()).prop_map(|(from, value)| Op::Burn { from, value }),
+    ]
+}
+
+/// Straightforward PSP22 ledger the token must agree with
+///
+/// The off-chain engine does not roll back failed messages, so like the
+/// token the model keeps the allowance spent by a `transfer_from` whose
+/// transfer then fails.
+#[derive(Debug)]
+struct Model {
+    total_supply: u128,
+    balances: BTreeMap<usize, u128>,
+    allowances: BTreeMap<(usize, usize), u128>,
+}
+
+impl Model {
+    fn balance_of(&self, account: usize) -> u128 {
+        self.balances.get(&account).copied().unwrap_or(0)
+    }
+
+    fn allowance(&self, owner: usize, spender: usize) -> u128 {
+        self.allowances.get(&(owner, spender)).copied().unwrap_or(0)
+    }
+
+    fn transfer(&mut self, from: usize, to: usize, value: u128) -> Result<(), PSP22Error> {
+        if from == to || value == 0 {
+            return Ok(());
+        }
+
+        let from_balance = self.balance_of(from);
+        if from_balance < value {
+            return Err(PSP22Error::InsufficientBalance);
+        }
+        let to_balance = self
+            .balance_of(to)
+            .checked_add(value)
+            .ok_or(PSP22Error::Custom(String::from("Overflow")))?;
+
+        self.balances.insert(from, from_balance - value);
+        self.balances.insert(to, to_balance);
+        Ok(())
+    }
+
+    fn approve(&mut self, owner: usize, spender: usize, value: u128) -> Result<(), PSP22Error> {
+        if owner != spender {
+            self.allowances.insert((owner, spender), value);
+        }
+        Ok(())
+    }
+
+    fn transfer_from(&mut self, spender: usize, from: usize, to: usize, value: u128) -> Result<(), PSP22Error> {
+        if from == to || value == 0 {
+            return Ok(());
+        }
+
+        let allowance = self.allowance(from, spender);
+        if spender != from && allowance != u128::MAX {
+            let remaining = allowance
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientAllowance)?;
+            self.allowances.insert((from, spender), remaining);
+        }
+
+        self.transfer(from, to, value)
+    }
+
+    fn mint(&mut self, to: usize, value: u128) -> Result<(), PSP22Error> {
+        if value == 0 {
+            return Ok(());
+        }
+
+        let balance = self
+            .balance_of(to)
+            .checked_add(value)
+            .ok_or(PSP22Error::Custom(String::from("Balance overflow")))?;
+        self.total_supply = self
+            .total_supply
+            .checked_add(value)
+            .ok_or(PSP22Error::Custom(String::from("Max supply exceeded")))?;
+
+        self.balances.insert(to, balance);
+        Ok(())
+    }
+
+    fn burn(&mut self, from: usize, value: u128) -> Result<(), PSP22Error> {
+        if value == 0 {
+            return Ok(());
+        }
+
+        let balance = self.balance_of(from);
+        if balance < value {
+            return Err(PSP22Error::InsufficientBalance);
+        }
+
+        self.balances.insert(from, balance - value);
+        self.total_supply -= value;
+        Ok(())
+    }
+}
+
+#[ink::test]
+fn token_matches_reference_model() {
+    let default_accounts = ink::env::test::default_accounts();
+    let accounts = [
+        default_accounts.alice,
+        default_accounts.bob,
+        default_accounts.charlie,
+        default_accounts.django,
+    ];
+
+    let mut runner = TestRunner::new(Config {
+        cases: 256,
+        failure_persistence: None,
+        ..Config::default()
+    });
+    let deployments = Cell::new(0u64);
+
+    let result = runner.run(&prop::collection::vec(op(), 1..32), |ops| {
+        // Deploy every case at a fresh address so no storage is shared
+        deployments.set(deployments.get() + 1);
+        let mut address = [0xC0; 20];
+        address[12..].copy_from_slice(&deployments.get().to_be_bytes());
+        ink::env::test::set_callee(Address::from(address));
+
+        ink::env::test::set_caller(accounts[0]);
+        let mut token = PspCoin::new_with_supply(INITIAL_SUPPLY);
+        let mut model = Model {
+            total_supply: INITIAL_SUPPLY,
+            balances: BTreeMap::from([(0, INITIAL_SUPPLY)]),
+            allowances: BTreeMap::new(),
+        };
+
+        for op in ops {
+            let (actual, expected) = match op {
+                Op::Transfer { from, to, value } => {
+                    ink::env::test::set_caller(accounts[from]);
+                    (
+                        token.transfer(accounts[to], value, Vec::new()),
+                        model.transfer(from, to, value),
+                    )
+                }
+                Op::Approve { owner, spender, value } => {
+                    ink::env::test::set_caller(accounts[owner]);
+                    (
+                        token.approve(accounts[spender], value),
+                        model.approve(owner, spender, value),
+                    )
+                }
+                Op::TransferFrom { spender, from, to, value } => {
+                    ink::env::test::set_caller(accounts[spender]);
+                    (
+                        token.transfer_from(accounts[from], accounts[to], value, Vec::new()),
+                        model.transfer_from(spender, from, to, value),
+                    )
+                }
+                Op::Mint { to, value } => {
+                    ink::env::test::set_caller(accounts[0]);
+                    (token.mint_to(accounts[to], value), model.mint(to, value))
+                }
+                Op::Burn { from, value } => {
+                    ink::env::test::set_caller(accounts[from]);
+                    (token.burn(value), model.burn(from, value))
+                }
+            };
+            prop_assert_eq!(actual, expected);
+
+            prop_assert_eq!(token.total_supply(), model.total_supply);
+            for (index, account) in accounts.iter().enumerate() {
+                prop_assert_eq!(token.balance_of(*account), model.balance_of(index));
+                for (spender_index, spender) in accounts.iter().enumerate() {
+                    prop_assert_eq!(
+                        token.allowance(*account, *spender),
+                        model.allowance(index, spender_index)
+                    );
+                }
+            }
+
+            let holders = (0..ACCOUNTS).filter(|index| model.balance_of(*index) > 0).count();
+            prop_assert_eq!(token.holders_count() as usize, holders);
+        }
+
+        Ok(())
+    });
+
+    if let Err(failure) = result {
+        panic!("{failure}");
+    }
+}
@@ -0,0 +1,157 @@
+//! Multi-account scenarios run against an in-memory runtime
+//!
+//! These use the `drink`-based sandbox backend of `ink_e2e`: the contract
+//! is compiled and deployed as on a node, with real dispatch and events,
+//! but without starting one, so each test takes seconds. Copy a test and
+//! change the calls to cover a new scenario.
+
+use ink::primitives::AccountId;
+use ink::prelude::string::String;
+use ink_e2e::ContractsBackend;
+
+use crate::{PSP22Error, PspCoin, PspCoinRef};
+
+type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[ink_e2e::test(backend(runtime_only(sandbox = ink_e2e::DefaultSandbox)))]
+async fn allowance_race_is_caught<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+    // Given alice approved bob for 100 tokens
+    let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+    let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+    let charlie = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::charlie());
+
+    let mut constructor = PspCoinRef::new_with_supply(1_000);
+    let token = client
+        .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+        .submit()
+        .await
+        .expect("token instantiate failed");
+    let mut call = token.call_builder::<PspCoin>();
+
+    client
+        .call(&ink_e2e::alice(), &call.approve(bob, 100))
+        .submit()
+        .await?;
+
+    // When bob spends 60 before alice's change to 50 lands
+    client
+        .call(&ink_e2e::bob(), &call.transfer_from(alice, charlie, 60, Vec::new()))
+        .submit()
+        .await?;
+
+    // Then the compare-and-set approval refuses to hand bob another 50
+    let raced = client
+        .call(&ink_e2e::alice(), &call.approve_from_to(bob, 100, 50))
+        .dry_run()
+        .await?
+        .return_value();
+    assert_eq!(raced, Err(PSP22Error::AllowanceChanged));
+
+    // And succeeds against the allowance bob actually has left
+    client
+        .call(&ink_e2e::alice(), &call.approve_from_to(bob, 40, 50))
+        .submit()
+        .await?;
+    let allowance = client
+        .call(&ink_e2e::alice(), &call.allowance(alice, bob))
+        .dry_run()
+        .await?
+        .return_value();
+    assert_eq!(allowance, 50);
+
+    Ok(())
+}
+
+#[ink_e2e::test(backend(runtime_only(sandbox = ink_e2e::DefaultSandbox)))]
+async fn pause_stops_pending_spends<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+    // Given bob holds an allowance from alice
+    let alice = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::alice());
+    let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+    let mut constructor = PspCoinRef::new_with_supply(1_000);
+    let token = client
+        .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+        .submit()
+        .await
+        .expect("token instantiate failed");
+    let mut call = token.call_builder::<PspCoin>();
+
+    client
+        .call(&ink_e2e::alice(), &call.approve(bob, 100))
+        .submit()
+        .await?;
+
+    // When alice pauses the token before bob spends it
+    client.call(&ink_e2e::alice(), &call.pause()).submit().await?;
+
+    // Then bob's spend fails
+    let paused = client
+        .call(&ink_e2e::bob(), &call.transfer_from(alice, bob, 100, Vec::new()))
+        .dry_run()
+        .await?
+        .return_value();
+    assert_eq!(paused, Err(PSP22Error::Custom(String::from("Token paused"))));
+
+    // And goes through once the token is unpaused
+    client.call(&ink_e2e::alice(), &call.unpause()).submit().await?;
+    client
+        .call(&ink_e2e::bob(), &call.transfer_from(alice, bob, 100, Vec::new()))
+        .submit()
+        .await?;
+    let balance = client
+        .call(&ink_e2e::bob(), &call.balance_of(bob))
+        .dry_run()
+        .await?
+        .return_value();
+    assert_eq!(balance, 100);
+
+    Ok(())
+}
+
+#[ink_e2e::test(backend(runtime_only(sandbox = ink_e2e::DefaultSandbox)))]
+async fn upgrade_keeps_balances<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+    // Given a token with balances and uploaded token code
+    let bob = ink_e2e::address_from_keypair::<AccountId>(&ink_e2e::bob());
+
+    let code_hash = client
+        .upload("psp_coin", &ink_e2e::alice())
+        .submit()
+        .await
+        .expect("token upload failed")
+        .code_hash;
+
+    let mut constructor = PspCoinRef::new_with_supply(1_000);
+    let token = client
+        .instantiate("psp_coin", &ink_e2e::alice(), &mut constructor)
+        .submit()
+        .await
+        .expect("token instantiate failed");
+    let mut call = token.call_builder::<PspCoin>();
+
+    client
+        .call(&ink_e2e::alice(), &call.transfer(bob, 250, Vec::new()))
+        .submit()
+        .await?;
+
+    // When bob tries to upgrade the token, the call is refused
+    let refused = client
+        .call(&ink_e2e::bob(), &call.upgrade(code_hash))
+        .dry_run()
+        .await?
+        .return_value();
+    assert_eq!(refused, Err(PSP22Error::Unauthorized));
+
+    // Then the owner's upgrade keeps every balance
+    client
+        .call(&ink_e2e::alice(), &call.upgrade(code_hash))
+        .submit()
+        .await?;
+    let balance = client
+        .call(&ink_e2e::bob(), &call.balance_of(bob))
+        .dry_run()
+        .await?
+        .return_value();
+    assert_eq!(balance, 250);
+
+    Ok(())
+}